@@ -3,7 +3,7 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 
 fn bench_normal(c: &mut Criterion, gen: fn(usize) -> (String, String), group_name: &str) {
-    use ojcmp::{try_normal_compare, Comparison};
+    use ojcmp::{try_normal_compare, ByteReader, Comparison, TextOptions};
 
     let mut group = c.benchmark_group(group_name);
     let ns = [
@@ -15,10 +15,12 @@ fn bench_normal(c: &mut Criterion, gen: fn(usize) -> (String, String), group_nam
         group.throughput(Throughput::Elements(n));
         group.bench_with_input(BenchmarkId::from_parameter(n), input, |b, (s, u)| {
             b.iter(|| {
-                let mut s_reader = s.as_bytes();
-                let mut u_reader = u.as_bytes();
+                let mut s_reader = ByteReader::with_capacity(64 * 1024, s.as_bytes());
+                let mut u_reader = ByteReader::with_capacity(64 * 1024, u.as_bytes());
                 assert_ne!(
-                    try_normal_compare(&mut s_reader, &mut u_reader).unwrap(),
+                    try_normal_compare(&mut s_reader, &mut u_reader, &TextOptions::new())
+                        .unwrap()
+                        .comparison,
                     Comparison::WA
                 );
             })
@@ -130,7 +132,9 @@ fn bench_float(c: &mut Criterion) {
     }
 
     use ojcmp::try_float_compare;
-    use ojcmp::Comparison;
+    use ojcmp::{ByteReader, Comparison, FloatOptions};
+
+    let options = FloatOptions::new(EPS);
 
     let group_name = "spj_float";
     let mut group = c.benchmark_group(group_name);
@@ -143,10 +147,12 @@ fn bench_float(c: &mut Criterion) {
         group.throughput(Throughput::Elements(n));
         group.bench_with_input(BenchmarkId::from_parameter(n), input, |b, (s, u)| {
             b.iter(|| {
-                let mut s_reader = s.as_bytes();
-                let mut u_reader = u.as_bytes();
+                let mut s_reader = ByteReader::with_capacity(64 * 1024, s.as_bytes());
+                let mut u_reader = ByteReader::with_capacity(64 * 1024, u.as_bytes());
                 assert_eq!(
-                    try_float_compare(&mut s_reader, &mut u_reader, EPS).unwrap(),
+                    try_float_compare(&mut s_reader, &mut u_reader, &options)
+                        .unwrap()
+                        .comparison,
                     Comparison::AC
                 );
             })
@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::fs::File;
 use std::io::{self, BufRead, Read};
 use std::panic::panic_any;
@@ -29,6 +30,72 @@ impl IoByte {
     }
 }
 
+/// A location in a byte stream.
+///
+/// `offset` counts bytes from the start of the stream.
+/// `line` and `column` are 1-based. A `b'\n'` belongs to the line it terminates.
+/// Both are 0 if the reader does not track lines, see [`ByteReader::set_track_lines`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: u64,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Position {
+    pub const START: Position = Position {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    /// Returns the position right after `bytes`, which start at `self`.
+    pub fn advance(self, bytes: &[u8]) -> Self {
+        let len = bytes.len() as u64;
+        match memchr::memrchr(b'\n', bytes) {
+            None => Self {
+                offset: self.offset + len,
+                line: self.line,
+                column: self.column + len,
+            },
+            Some(idx) => {
+                let lines = memchr::memchr_iter(b'\n', bytes).count() as u64;
+                Self {
+                    offset: self.offset + len,
+                    line: self.line + lines,
+                    column: (bytes.len() - idx) as u64,
+                }
+            }
+        }
+    }
+}
+
+impl Position {
+    /// Returns the position at `offset` in the stream read from `reader`.
+    ///
+    /// Lines are counted from the start of the stream, so this is meant to be called once,
+    /// for a position taken from a reader which does not track lines.
+    pub fn locate(mut reader: impl Read, offset: u64) -> io::Result<Self> {
+        let mut pos = Self::START;
+        let mut buf = vec![0; 64 * 1024];
+        while pos.offset < offset {
+            let amt = (offset - pos.offset).min(buf.len() as u64) as usize;
+            let nread = reader.read(&mut buf[..amt])?;
+            if nread == 0 {
+                break;
+            }
+            pos = pos.advance(&buf[..nread]);
+        }
+        Ok(pos)
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
 pub trait ByteRead: BufRead {
     fn next_byte(&mut self) -> IoByte;
 
@@ -36,6 +103,21 @@ pub trait ByteRead: BufRead {
     unsafe fn consume_unchecked(&mut self, amt: usize);
 }
 
+/// A [`ByteRead`] which knows the positions of its bytes in the stream.
+/// Comparisons take it to report where the files differ.
+///
+/// A plain slice does not know where it started, so it is not one.
+/// Wrap it in a [`ByteReader`] to compare it.
+pub trait TrackedRead: ByteRead {
+    /// Returns the position of the next byte to be consumed.
+    fn position(&self) -> Position;
+
+    /// Returns the position of the last consumed byte.
+    ///
+    /// Returns [`Position::START`] if nothing has been consumed.
+    fn last_position(&self) -> Position;
+}
+
 impl ByteRead for &'_ [u8] {
     fn next_byte(&mut self) -> IoByte {
         match self {
@@ -51,6 +133,8 @@ impl ByteRead for &'_ [u8] {
     }
 }
 
+/// # Safety
+/// `trusted_read` must never report more bytes than the length of `buf`.
 pub unsafe trait TrustedRead: Read {
     #[inline]
     fn trusted_read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -61,12 +145,80 @@ pub unsafe trait TrustedRead: Read {
 
 unsafe impl TrustedRead for File {}
 
+unsafe impl TrustedRead for &'_ [u8] {}
+
+/// Folds consumed bytes into a [`Position`] lazily.
+///
+/// Bytes before `ptr` are already counted, so the hot paths never touch it.
+#[derive(Debug)]
+struct Tracker {
+    ptr: Cell<*const u8>,
+    pos: Cell<Position>,
+    last: Cell<Position>,
+    /// Counts lines and columns besides offsets.
+    lines: bool,
+}
+
+impl Tracker {
+    fn new() -> Self {
+        Self {
+            ptr: Cell::new(ptr::null()),
+            pos: Cell::new(Position::START),
+            last: Cell::new(Position::START),
+            lines: true,
+        }
+    }
+
+    fn set_lines(&mut self, lines: bool) {
+        let start = if lines {
+            Position::START
+        } else {
+            Position {
+                offset: 0,
+                line: 0,
+                column: 0,
+            }
+        };
+        self.pos.set(start);
+        self.last.set(start);
+        self.lines = lines;
+    }
+
+    /// Counts bytes in `[self.ptr, end)`.
+    ///
+    /// # Safety
+    /// `[self.ptr, end)` must be a valid range in the current buffer.
+    unsafe fn sync(&self, end: *const u8) {
+        let start = self.ptr.get();
+        if start.is_null() || end <= start {
+            return;
+        }
+        let len = end as usize - start as usize;
+        let pos = self.pos.get();
+        if self.lines {
+            let bytes = slice::from_raw_parts(start, len);
+            let last = pos.advance(&bytes[..len - 1]);
+            self.last.set(last);
+            self.pos.set(last.advance(&bytes[len - 1..]));
+        } else {
+            let offset = pos.offset + len as u64;
+            self.last.set(Position {
+                offset: offset - 1,
+                ..pos
+            });
+            self.pos.set(Position { offset, ..pos });
+        }
+        self.ptr.set(end);
+    }
+}
+
 #[derive(Debug)]
 pub struct ByteReader<R> {
     inner: R,
     buf: Box<[u8]>,
     head: *const u8,
     tail: *const u8,
+    tracker: Tracker,
 }
 
 impl<R: Read> ByteReader<R> {
@@ -76,6 +228,7 @@ impl<R: Read> ByteReader<R> {
             buf: vec![0; capacity].into(),
             head: ptr::null(),
             tail: ptr::null(),
+            tracker: Tracker::new(),
         }
     }
 
@@ -86,9 +239,19 @@ impl<R: Read> ByteReader<R> {
             buf: Box::from_raw(buf),
             head: ptr::null(),
             tail: ptr::null(),
+            tracker: Tracker::new(),
         }
     }
 
+    /// Enables or disables line and column tracking, which is on by default.
+    ///
+    /// Without it only offsets are kept, which saves counting newlines in every byte read.
+    /// [`Position::locate`] can recover line and column of an offset afterwards.
+    /// Must be called before reading.
+    pub fn set_track_lines(&mut self, enabled: bool) {
+        self.tracker.set_lines(enabled);
+    }
+
     #[allow(clippy::missing_safety_doc)]
     pub unsafe fn into_raw(self) -> (*mut [u8], R) {
         let buf = Box::into_raw(self.buf);
@@ -109,7 +272,9 @@ impl<R: TrustedRead> BufRead for ByteReader<R> {
             let len = self.tail as usize - self.head as usize;
             Ok(unsafe { slice::from_raw_parts(self.head, len) })
         } else {
-            let nread = self.inner.trusted_read(&mut *self.buf)?;
+            unsafe { self.tracker.sync(self.tail) };
+            let nread = self.inner.trusted_read(&mut self.buf)?;
+            self.tracker.ptr.set(self.buf.as_ptr());
             if nread == 0 {
                 self.head = ptr::null();
                 self.tail = ptr::null();
@@ -139,9 +304,13 @@ impl<R: TrustedRead> ByteRead for ByteReader<R> {
                 IoByte::from_u8(byte)
             }
         } else {
-            match self.inner.trusted_read(&mut *self.buf) {
+            unsafe { self.tracker.sync(self.tail) };
+            match self.inner.trusted_read(&mut self.buf) {
                 Ok(nread) => {
+                    self.tracker.ptr.set(self.buf.as_ptr());
                     if nread == 0 {
+                        self.head = ptr::null();
+                        self.tail = ptr::null();
                         IoByte::EOF
                     } else {
                         unsafe {
@@ -161,6 +330,18 @@ impl<R: TrustedRead> ByteRead for ByteReader<R> {
     }
 }
 
+impl<R: TrustedRead> TrackedRead for ByteReader<R> {
+    fn position(&self) -> Position {
+        unsafe { self.tracker.sync(self.head) };
+        self.tracker.pos.get()
+    }
+
+    fn last_position(&self) -> Position {
+        unsafe { self.tracker.sync(self.head) };
+        self.tracker.last.get()
+    }
+}
+
 #[cfg(unix)]
 pub mod unix {
     use super::TrustedRead;
@@ -204,3 +385,26 @@ pub mod unix {
         }
     }
 }
+
+#[test]
+fn test_position_locate() {
+    let data: &[u8] = b"ab\ncd\n\nef";
+
+    let mut tracked = ByteReader::with_capacity(3, data);
+    let mut untracked = ByteReader::with_capacity(3, data);
+    untracked.set_track_lines(false);
+
+    for offset in 0..data.len() as u64 {
+        let pos = tracked.position();
+        let raw = untracked.position();
+        assert_eq!(pos.offset, offset);
+        assert_eq!((raw.offset, raw.line, raw.column), (offset, 0, 0));
+        assert_eq!(Position::locate(data, raw.offset).unwrap(), pos);
+
+        tracked.next_byte();
+        untracked.next_byte();
+    }
+
+    let end = Position::locate(data, 100).unwrap();
+    assert_eq!((end.offset, end.line, end.column), (9, 4, 3));
+}
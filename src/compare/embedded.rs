@@ -0,0 +1,563 @@
+use super::float::{show_literal, within_eps};
+use super::{catch_io, CompareError, CompareReport, Comparison, FloatMismatch};
+use super::{LiteralPolicy, Mismatch, MismatchReason};
+
+use crate::byte_read::{Position, TrackedRead};
+
+use std::panic::{panic_any, AssertUnwindSafe};
+
+pub fn try_embedded_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &EmbeddedOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        embedded_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)?
+}
+
+/// Options of embedded mode.
+///
+/// Each line is split into numbers, like "3.14" in "x=3.14", and literal runs between them.
+/// Literal runs must be equal byte by byte, while a user number passes
+/// if its error to std is within `eps` or relatively within `rel_eps`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedOptions {
+    /// Max absolute error
+    pub eps: f64,
+    /// Max relative error to std. Zero disables the relative check.
+    pub rel_eps: f64,
+    /// Max bytes of each current line held in memory. `None` means no limit.
+    /// A std line exceeding it is an error, while a user line exceeding it gives WA.
+    ///
+    /// Each number or literal run of the lines takes another 40 bytes or so, which are not counted.
+    pub max_memory: Option<usize>,
+}
+
+impl EmbeddedOptions {
+    pub fn new(eps: f64) -> Self {
+        Self {
+            eps,
+            rel_eps: 0.0,
+            max_memory: None,
+        }
+    }
+
+    /// Whether user value `b` is close enough to std value `a`.
+    fn is_close(&self, a: f64, b: f64) -> bool {
+        within_eps(a, b, (b - a).abs(), self.eps, self.rel_eps)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// A run of bytes which are neither whitespaces nor numbers
+    Literal,
+    Number(f64),
+}
+
+/// A non-whitespace piece of a line.
+#[derive(Debug)]
+struct Segment {
+    start: usize,
+    end: usize,
+    /// Start of the whitespaces before the segment
+    space: usize,
+    kind: Kind,
+}
+
+/// A point in a line: inside segment `k`, or at the end of the line.
+#[derive(Debug, Clone, Copy)]
+struct Cursor {
+    k: usize,
+    at: usize,
+}
+
+/// A line with trailing whitespaces trimmed.
+#[derive(Debug, Default)]
+struct Line {
+    bytes: Vec<u8>,
+    /// Position of the first byte
+    pos: Position,
+    segments: Vec<Segment>,
+    /// Whether the line is cut after its last segment, as it exceeds the memory limit
+    truncated: bool,
+}
+
+impl Line {
+    fn position(&self, idx: usize) -> Position {
+        Position {
+            offset: self.pos.offset + idx as u64,
+            line: self.pos.line,
+            column: self.pos.column + idx as u64,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.bytes.clear();
+        self.segments.clear();
+        self.truncated = false;
+    }
+
+    fn literal(&self, s: &Segment) -> Option<String> {
+        show_literal(&self.bytes[s.start..s.end])
+    }
+
+    /// Cuts the line read so far after its last whole segment.
+    fn truncate(&mut self) {
+        self.split();
+        // the last segment may continue in the unread bytes
+        if let Some(s) = self.segments.pop() {
+            self.bytes.truncate(s.space);
+        }
+        self.truncated = true;
+    }
+
+    /// The start of segment `k`.
+    fn cursor(&self, k: usize) -> Cursor {
+        let at = self.segments.get(k).map_or(self.bytes.len(), |s| s.start);
+        Cursor { k, at }
+    }
+
+    /// Moves `c` to `at` in its segment, or to the next segment at the end of it.
+    fn advance(&self, c: Cursor, at: usize) -> Cursor {
+        if at < self.segments[c.k].end {
+            Cursor { k: c.k, at }
+        } else {
+            self.cursor(c.k + 1)
+        }
+    }
+
+    /// Whitespaces before `c`, which are empty inside a literal run.
+    fn space(&self, c: Cursor) -> &[u8] {
+        match self.segments.get(c.k) {
+            Some(s) if s.start == c.at => &self.bytes[s.space..s.start],
+            _ => &[],
+        }
+    }
+
+    fn split(&mut self) {
+        let policy = LiteralPolicy::new();
+        let number_at = |bytes: &[u8], i: usize| {
+            let end = scan_number(bytes, i)?;
+            policy.parse(&bytes[i..end]).ok().map(|x| (end, x))
+        };
+
+        self.segments.clear();
+        let mut space = 0;
+        let mut i = 0;
+        while i < self.bytes.len() {
+            if self.bytes[i].is_ascii_whitespace() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let kind = match number_at(&self.bytes, i) {
+                Some((end, x)) => {
+                    i = end;
+                    Kind::Number(x)
+                }
+                None => {
+                    i += 1;
+                    while i < self.bytes.len()
+                        && !self.bytes[i].is_ascii_whitespace()
+                        && number_at(&self.bytes, i).is_none()
+                    {
+                        i += 1;
+                    }
+                    Kind::Literal
+                }
+            };
+            self.segments.push(Segment {
+                start,
+                end: i,
+                space,
+                kind,
+            });
+            space = i;
+        }
+    }
+}
+
+/// Finds the end of a number starting at `i`, like `-1.5e3` or `.5`.
+///
+/// A number does not start right after a letter, a digit or '_', so "x1" is a literal run.
+fn scan_number(s: &[u8], i: usize) -> Option<usize> {
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let digits_from = |j: usize| j + s[j..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    if i > 0 && is_word(s[i - 1]) {
+        return None;
+    }
+    let mut j = i;
+    if s[j] == b'+' || s[j] == b'-' {
+        j += 1;
+    }
+    let int_end = digits_from(j);
+    let mut has_digit = int_end > j;
+    j = int_end;
+    if s.get(j) == Some(&b'.') {
+        let frac_end = digits_from(j + 1);
+        if has_digit || frac_end > j + 1 {
+            has_digit |= frac_end > j + 1;
+            j = frac_end;
+        }
+    }
+    if !has_digit {
+        return None;
+    }
+    if matches!(s.get(j), Some(b'e') | Some(b'E')) {
+        let mut k = j + 1;
+        if matches!(s.get(k), Some(b'+') | Some(b'-')) {
+            k += 1;
+        }
+        let exp_end = digits_from(k);
+        if exp_end > k {
+            j = exp_end;
+        }
+    }
+    Some(j)
+}
+
+/// Reads a line with trailing whitespaces trimmed.
+/// Returns false if the reader has ended.
+///
+/// A line longer than `limit` bytes is an error.
+fn read_line(
+    reader: &mut impl TrackedRead,
+    line: &mut Line,
+    limit: usize,
+) -> Result<bool, CompareError> {
+    line.clear();
+    line.pos = reader.position();
+    let mut byte = reader.next_byte();
+    if byte.is_eof() {
+        return Ok(false);
+    }
+    while !byte.is_eof() && byte.as_u8() != b'\n' {
+        if line.bytes.len() >= limit {
+            return Err(CompareError::TooLarge);
+        }
+        line.bytes.push(byte.as_u8());
+        byte = reader.next_byte();
+    }
+    while line.bytes.last().is_some_and(u8::is_ascii_whitespace) {
+        line.bytes.pop();
+    }
+    line.split();
+    Ok(true)
+}
+
+/// Reads a user line like [`read_line`].
+/// A line longer than `limit` bytes is truncated, and gives WA when it is compared.
+fn read_user_line(
+    reader: &mut impl TrackedRead,
+    line: &mut Line,
+    limit: usize,
+) -> Result<bool, CompareError> {
+    match read_line(reader, line, limit) {
+        Err(CompareError::TooLarge) => {
+            line.truncate();
+            Ok(true)
+        }
+        ret => ret,
+    }
+}
+
+fn at_eof(reader: &mut impl TrackedRead) -> bool {
+    match reader.fill_buf() {
+        Ok(buf) => buf.is_empty(),
+        Err(e) => panic_any(e),
+    }
+}
+
+fn number_mismatch(
+    token_index: u64,
+    std: &Line,
+    a: (&Segment, f64),
+    user: &Line,
+    b: (&Segment, f64),
+) -> FloatMismatch {
+    FloatMismatch::new(
+        token_index,
+        (std.literal(a.0), Some(a.1)),
+        (user.literal(b.0), Some(b.1)),
+        Some((b.1 - a.1).abs()),
+    )
+}
+
+fn embedded_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &EmbeddedOptions,
+) -> Result<CompareReport, CompareError> {
+    let limit = options.max_memory.unwrap_or(usize::MAX);
+
+    let mut std = Line::default();
+    let mut user = Line::default();
+    // numbers compared so far
+    let mut token_index: u64 = 0;
+    // the first whitespace difference which gives PE
+    let mut format_failure: Option<Mismatch> = None;
+
+    let failure = 'lines: loop {
+        user.clear();
+        let std_more = read_line(std_reader, &mut std, limit)?;
+        let user_more = read_user_line(user_reader, &mut user, limit)?;
+
+        // trim_end(file)
+        if !std_more || !user_more {
+            while std_more && std.segments.is_empty() && read_line(std_reader, &mut std, limit)? {}
+            while user_more
+                && user.segments.is_empty()
+                && !user.truncated
+                && read_user_line(user_reader, &mut user, limit)?
+            {}
+            let reason = match (std.segments.is_empty(), user.segments.is_empty()) {
+                (true, true) if !user.truncated => break None,
+                (true, _) => MismatchReason::ExtraOutput,
+                (false, _) => MismatchReason::MissingOutput,
+            };
+            let mismatch = Mismatch {
+                std: std.position(std.cursor(0).at),
+                user: user.position(user.cursor(0).at),
+                reason,
+            };
+            break Some((mismatch, None));
+        }
+
+        let (mut ca, mut cb) = (std.cursor(0), user.cursor(0));
+        loop {
+            let mismatch = |reason| Mismatch {
+                std: std.position(ca.at),
+                user: user.position(cb.at),
+                reason,
+            };
+            let (a, b) = match (std.segments.get(ca.k), user.segments.get(cb.k)) {
+                (Some(a), Some(b)) => (a, b),
+                (None, None) if !user.truncated => break,
+                (a, _) => {
+                    // one line ends while the other continues
+                    let reason = match a {
+                        // the rest of a truncated line is unknown
+                        Some(_) if user.truncated => MismatchReason::TokenMismatch,
+                        Some(_) if at_eof(user_reader) => MismatchReason::MissingOutput,
+                        None if at_eof(std_reader) => MismatchReason::ExtraOutput,
+                        _ => MismatchReason::LineBreakMismatch,
+                    };
+                    break 'lines Some((mismatch(reason), None));
+                }
+            };
+
+            let (next_a, next_b) = match (a.kind, b.kind) {
+                (Kind::Literal, Kind::Literal) => {
+                    // literal runs may be split differently by whitespaces
+                    let len = (a.end - ca.at).min(b.end - cb.at);
+                    let x = &std.bytes[ca.at..ca.at + len];
+                    let y = &user.bytes[cb.at..cb.at + len];
+                    if let Some(d) = x.iter().zip(y).position(|(x, y)| x != y) {
+                        let mismatch = Mismatch {
+                            std: std.position(ca.at + d),
+                            user: user.position(cb.at + d),
+                            reason: MismatchReason::TokenMismatch,
+                        };
+                        break 'lines Some((mismatch, None));
+                    }
+                    (std.advance(ca, ca.at + len), user.advance(cb, cb.at + len))
+                }
+                (Kind::Number(x), Kind::Number(y)) => {
+                    token_index += 1;
+                    if !options.is_close(x, y) {
+                        let m = number_mismatch(token_index, &std, (a, x), &user, (b, y));
+                        break 'lines Some((mismatch(MismatchReason::TokenMismatch), Some(m)));
+                    }
+                    (std.cursor(ca.k + 1), user.cursor(cb.k + 1))
+                }
+                _ => break 'lines Some((mismatch(MismatchReason::TokenMismatch), None)),
+            };
+            if format_failure.is_none() && std.space(ca) != user.space(cb) {
+                format_failure = Some(mismatch(MismatchReason::WhitespaceOnly));
+            }
+            ca = next_a;
+            cb = next_b;
+        }
+    };
+
+    let std_consumed = std_reader.position().offset;
+    let user_consumed = user_reader.position().offset;
+    Ok(match (failure, format_failure) {
+        (Some((mismatch, float_mismatch)), _) => {
+            let mut report =
+                CompareReport::new(Comparison::WA, Some(mismatch), std_consumed, user_consumed);
+            report.float_mismatch = float_mismatch;
+            report
+        }
+        (None, Some(mismatch)) => {
+            CompareReport::new(Comparison::PE, Some(mismatch), std_consumed, user_consumed)
+        }
+        (None, None) => CompareReport::new(Comparison::AC, None, std_consumed, user_consumed),
+    })
+}
+
+#[test]
+fn test_embedded_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let options = EmbeddedOptions::new(1e-6);
+                let ret = embedded_compare(&mut std, &mut user, &options).unwrap();
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"", b"");
+    judge!(AC, b"(1.000000,2.500000)", b"(1,2.5)");
+    judge!(AC, b"x=3.14", b"x=3.1400000001");
+    judge!(AC, b"Case #1: -2.5e3\n", b"Case #1: -2500.0000000\r\n\n");
+    judge!(AC, b"[.5, 1.]", b"[0.5, 1]");
+    judge!(AC, b"x1 y2", b"x1 y2");
+    judge!(AC, b"1 2\n", b"1 2  \n\n");
+    judge!(WA, b"x=3.14", b"x=3.15");
+    judge!(WA, b"x=3.14", b"y=3.14");
+    judge!(WA, b"x1", b"x1.0");
+    judge!(WA, b"(1,2)", b"(1,2,3)");
+    judge!(WA, b"(1,2)", b"(1;2)");
+    judge!(WA, b"1 2", b"12");
+    judge!(WA, b"1 2", b"1\n2");
+    judge!(WA, b"a\n\nb", b"a\nb");
+    judge!(WA, b"x=3", b"x=");
+    judge!(WA, b"x=3", b"x=three");
+    judge!(WA, b"1", b"1 2");
+    judge!(WA, b"1 2", b"1");
+    judge!(WA, b"1\n", b"1\n\n2");
+    judge!(PE, b"x=3.14", b"x = 3.14");
+    judge!(PE, b"(1, 2)", b"(1,2)");
+    judge!(PE, b"a\tb", b"a b");
+    judge!(WA, b"(1, 2)", b"(1,3)");
+    judge!(PE, b"ab c", b"a bc");
+    judge!(WA, b"ab c", b"a bd");
+}
+
+#[test]
+fn test_embedded_scan_number() {
+    fn scan(s: &[u8]) -> Option<&[u8]> {
+        scan_number(s, 0).map(|end| &s[..end])
+    }
+
+    assert_eq!(scan(b"-1.5e3,"), Some(&b"-1.5e3"[..]));
+    assert_eq!(scan(b".5)"), Some(&b".5"[..]));
+    assert_eq!(scan(b"1.)"), Some(&b"1."[..]));
+    assert_eq!(scan(b"2e"), Some(&b"2"[..]));
+    assert_eq!(scan(b"2e+x"), Some(&b"2"[..]));
+    assert_eq!(scan(b"3x"), Some(&b"3"[..]));
+    assert_eq!(scan(b"-x"), None);
+    assert_eq!(scan(b"."), None);
+    assert_eq!(scan_number(b"x1", 1), None);
+    assert_eq!(scan_number(b"=-1", 1), Some(3));
+}
+
+#[test]
+fn test_embedded_report() {
+    use crate::byte_read::ByteReader;
+
+    let report_of = |std: &[u8], user: &[u8]| {
+        let mut options = EmbeddedOptions::new(1e-3);
+        options.rel_eps = 1e-6;
+        let mut std = ByteReader::with_capacity(16, std);
+        let mut user = ByteReader::with_capacity(16, user);
+        embedded_compare(&mut std, &mut user, &options).unwrap()
+    };
+
+    let ret = report_of(b"p=(1.0,2.0)\nq=(3.0,4.0)\n", b"p=(1,2)\nq=(3,4.5)\n");
+    assert_eq!(ret.comparison, Comparison::WA);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 8));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (2, 6));
+    let m = ret.float_mismatch.unwrap();
+    assert_eq!(m.token_index, 4);
+    assert_eq!(m.std_literal.as_deref(), Some("4.0"));
+    assert_eq!(m.user_literal.as_deref(), Some("4.5"));
+    assert_eq!(m.abs_error, Some(0.5));
+
+    assert_eq!(
+        report_of(b"1e12", b"1000000000100").comparison,
+        Comparison::AC
+    );
+
+    let ret = report_of(b"x = 1\ny=2", b"x = 1\ny =2");
+    assert_eq!(ret.comparison, Comparison::PE);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::WhitespaceOnly);
+    assert_eq!((mismatch.user.line, mismatch.user.column), (2, 3));
+
+    let reason_of = |std: &[u8], user: &[u8]| report_of(std, user).mismatch.unwrap().reason;
+    assert_eq!(
+        reason_of(b"1 2", b"1\n2"),
+        MismatchReason::LineBreakMismatch
+    );
+    assert_eq!(reason_of(b"1", b"1 2"), MismatchReason::ExtraOutput);
+    assert_eq!(reason_of(b"1\n2", b"1"), MismatchReason::MissingOutput);
+    assert_eq!(reason_of(b"x=1", b"x=y"), MismatchReason::TokenMismatch);
+
+    let ret = report_of(b"name: alice", b"name: alicf");
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!((mismatch.std.column, mismatch.user.column), (11, 11));
+}
+
+#[test]
+fn test_embedded_max_memory() {
+    use crate::byte_read::ByteReader;
+
+    let report_of = |std: &[u8], user: &[u8]| {
+        let mut options = EmbeddedOptions::new(1e-6);
+        options.max_memory = Some(4096);
+        let mut std = ByteReader::with_capacity(16, std);
+        let mut user = ByteReader::with_capacity(16, user);
+        let ret = embedded_compare(&mut std, &mut user, &options);
+        (ret, user.position().offset)
+    };
+    let judge = |std: &[u8], user: &[u8]| report_of(std, user).0.map(|r| r.comparison);
+
+    // only the bytes are counted
+    let text = [b"x=1 ".as_ref(), &[b'y'; 1000]].concat();
+    assert!(matches!(judge(&text, &text), Ok(Comparison::AC)));
+    let numbers = b"1,".repeat(1000);
+    assert!(matches!(judge(&numbers, &numbers), Ok(Comparison::AC)));
+
+    // a huge std line is an error
+    let huge = vec![b'x'; 1 << 20];
+    assert!(matches!(judge(&huge, b"x"), Err(CompareError::TooLarge)));
+
+    // a huge user line gives WA before it is fully buffered
+    let (ret, offset) = report_of(b"x", &huge);
+    assert_eq!(ret.unwrap().comparison, Comparison::WA);
+    assert!(offset <= 8192);
+    let padded = [b"x=1".as_ref(), &[b' '; 8192]].concat();
+    assert!(matches!(judge(b"x=1", &padded), Ok(Comparison::WA)));
+
+    // the part read is still compared
+    let long = [b"1 5 ".as_ref(), &b"3 ".repeat(4096)].concat();
+    let (ret, _) = report_of(b"1 2 3", &long);
+    let ret = ret.unwrap();
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!(mismatch.user.column, 3);
+    assert_eq!(ret.float_mismatch.unwrap().token_index, 2);
+    let long = [b"1 2 ".as_ref(), &b"3 ".repeat(4096)].concat();
+    let (ret, _) = report_of(b"1 2", &long);
+    assert_eq!(
+        ret.unwrap().mismatch.unwrap().reason,
+        MismatchReason::ExtraOutput
+    );
+}
@@ -1,94 +1,1159 @@
-use super::{catch_io, CompareError, Comparison};
+use super::{catch_io, CompareError, CompareReport, Comparison, Mismatch, MismatchReason};
 
-use crate::byte_read::ByteRead;
+use crate::byte_read::{Position, TrackedRead};
 
-use std::panic::AssertUnwindSafe;
+use std::cmp::Ordering;
+use std::panic::{panic_any, AssertUnwindSafe};
 
 pub fn try_float_compare(
-    std_reader: &mut impl ByteRead,
-    user_reader: &mut impl ByteRead,
-    eps: f64,
-) -> Result<Comparison, CompareError> {
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FloatOptions,
+) -> Result<CompareReport, CompareError> {
     catch_io(AssertUnwindSafe(move || {
-        float_compare(std_reader, user_reader, eps)
+        float_compare(std_reader, user_reader, options)
     }))
     .map_err(CompareError::Io)
 }
 
-fn float_compare(
-    std_reader: &mut impl ByteRead,
-    user_reader: &mut impl ByteRead,
-    eps: f64,
-) -> Comparison {
-    loop {
-        let std_f64 = match poll_f64(std_reader) {
-            Ok(o) => o,
-            Err(()) => return Comparison::WA,
+/// Options of float mode.
+///
+/// A token pair passes if its absolute error is within `eps`
+/// or its relative error is within `rel_eps`, like `doubleCompare` of testlib,
+/// or its distance is within `max_ulps`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatOptions {
+    /// Max absolute error
+    pub eps: f64,
+    /// Max relative error to std. Zero disables the relative check.
+    pub rel_eps: f64,
+    /// Max distance in units in the last place. `None` disables the ulp check.
+    pub max_ulps: Option<u64>,
+    /// Keeps comparing after the first failing token and collects [`FloatStats`].
+    pub full_scan: bool,
+    /// Classifies each std token as integer, float or word.
+    /// Integers are compared exactly by value, words are compared byte by byte,
+    /// and only floats are compared with eps.
+    pub mixed: bool,
+    /// Required digits after the decimal point of user floats. `None` disables the check.
+    pub decimals: Option<Decimals>,
+    /// Verdict when a user float is close enough but has wrong decimal places
+    /// or too few significant digits, PE or WA.
+    pub decimals_verdict: Comparison,
+    /// Significant digits policy. `None` disables it.
+    pub sig_figs: Option<SigFigs>,
+    /// Which float literals are accepted
+    pub literals: LiteralPolicy,
+    /// Tracks line breaks and whitespaces like normal mode.
+    /// Gives PE if all tokens pass but the layout differs.
+    pub lines: bool,
+    /// Max absolute error of each column, used instead of `eps` if not empty.
+    /// Columns cycle if there are more tokens than `eps_columns`.
+    pub eps_columns: Vec<f64>,
+    /// How tokens are assigned to `eps_columns` and `period_columns`
+    pub columns: Columns,
+    /// Compares values by circular distance modulo the period, like angles. `None` disables it.
+    pub period: Option<f64>,
+    /// Period of each column, used instead of `period` if not empty.
+    /// `None` for columns which are not periodic. Columns cycle like `eps_columns`.
+    pub period_columns: Vec<Option<f64>>,
+    /// Infers the max absolute error of each token from the std literal,
+    /// as half a unit in its last printed digit. Used instead of `eps` and `eps_columns`.
+    pub auto_eps: bool,
+    /// Aggregates the errors of all float tokens by a norm, which is checked at EOF
+    /// against `eps` and `rel_eps` instead of checking each token.
+    /// `max_ulps`, `eps_columns` and [`SigFigs::Round`] do not apply then.
+    pub norm: Option<Norm>,
+}
+
+/// A vector norm of the errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Norm {
+    /// `sqrt(sum(x^2))`
+    L2,
+    /// `max(|x|)`
+    LInf,
+}
+
+/// How tokens are assigned to columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Columns {
+    /// Columns restart at each std line.
+    Line,
+    /// Columns follow the token index over the whole output.
+    Token,
+}
+
+/// Which spellings of float literals are valid.
+///
+/// The default accepts decimal floats with exponents, a leading '+', NaN and infinities,
+/// and rejects hex floats and comma separators.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteralPolicy {
+    /// Accepts "nan", "inf" and "infinity" case-insensitively.
+    pub nan_inf: bool,
+    /// Treats NaN as equal to NaN.
+    pub nan_eq: bool,
+    /// Accepts exponent notation like "1e9".
+    pub exponent: bool,
+    /// Accepts a leading '+'.
+    pub plus_sign: bool,
+    /// Accepts hex floats like "0x1.8p3".
+    pub hex: bool,
+    /// Accepts ',' as the decimal separator, like "3,14".
+    pub comma: bool,
+}
+
+impl Default for LiteralPolicy {
+    fn default() -> Self {
+        Self {
+            nan_inf: true,
+            nan_eq: false,
+            exponent: true,
+            plus_sign: true,
+            hex: false,
+            comma: false,
+        }
+    }
+}
+
+impl LiteralPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn parse(&self, literal: &[u8]) -> Result<f64, ()> {
+        let (neg, unsigned) = match literal.first() {
+            Some(b'+') if self.plus_sign => (false, &literal[1..]),
+            Some(b'-') => (true, &literal[1..]),
+            Some(b'+') => return Err(()),
+            _ => (false, literal),
         };
 
-        let user_f64 = match poll_f64(user_reader) {
-            Ok(o) => o,
-            Err(()) => return Comparison::WA,
+        let value = match unsigned {
+            [b'+', ..] | [b'-', ..] => return Err(()),
+            // most tokens are plain decimals, which need no policy checks
+            [d, rest @ ..]
+                if d.is_ascii_digit()
+                    && !matches!(rest, [b'x', ..] | [b'X', ..])
+                    && self.exponent
+                    && !self.comma =>
+            {
+                lexical_core::parse::<f64>(unsigned).map_err(drop)?
+            }
+            _ if is_nan_inf(unsigned) => {
+                if !self.nan_inf {
+                    return Err(());
+                }
+                if unsigned.eq_ignore_ascii_case(b"nan") {
+                    f64::NAN
+                } else {
+                    f64::INFINITY
+                }
+            }
+            [b'0', x, digits @ ..] if x.eq_ignore_ascii_case(&b'x') => {
+                if !self.hex {
+                    return Err(());
+                }
+                parse_hex(digits)?
+            }
+            _ => {
+                if !self.exponent && unsigned.iter().any(|&b| b == b'e' || b == b'E') {
+                    return Err(());
+                }
+                if self.comma && unsigned.contains(&b',') {
+                    if unsigned.contains(&b'.') {
+                        return Err(());
+                    }
+                    let dotted: Vec<u8> = unsigned
+                        .iter()
+                        .map(|&b| if b == b',' { b'.' } else { b })
+                        .collect();
+                    lexical_core::parse::<f64>(&dotted).map_err(drop)?
+                } else {
+                    lexical_core::parse::<f64>(unsigned).map_err(drop)?
+                }
+            }
         };
+        Ok(if neg { -value } else { value })
+    }
+}
 
-        match (std_f64, user_f64) {
-            (Some(_), None) | (None, Some(_)) => return Comparison::WA,
+fn is_nan_inf(literal: &[u8]) -> bool {
+    [&b"nan"[..], b"inf", b"infinity"]
+        .iter()
+        .any(|s| literal.eq_ignore_ascii_case(s))
+}
 
-            (None, None) => return Comparison::AC,
+fn is_hex(literal: &[u8]) -> bool {
+    let unsigned = match literal.first() {
+        Some(b'+') | Some(b'-') => &literal[1..],
+        _ => literal,
+    };
+    matches!(unsigned, [b'0', x, ..] if x.eq_ignore_ascii_case(&b'x'))
+}
 
-            (Some(a), Some(b)) => {
-                let diff = (b - a).abs(); // check nan or +inf !!!
-                if let None | Some(std::cmp::Ordering::Greater) = diff.partial_cmp(&eps) {
-                    return Comparison::WA;
-                }
+/// Parses the digits of a hex float after "0x", like "1.8p3".
+fn parse_hex(digits: &[u8]) -> Result<f64, ()> {
+    let (mantissa, exponent) = match digits.iter().position(|&b| b == b'p' || b == b'P') {
+        Some(idx) => (&digits[..idx], Some(&digits[idx + 1..])),
+        None => (digits, None),
+    };
+
+    let mut exp: i64 = match exponent {
+        None => 0,
+        Some(e) => {
+            let (neg, e) = match e.first() {
+                Some(b'+') => (false, &e[1..]),
+                Some(b'-') => (true, &e[1..]),
+                _ => (false, e),
+            };
+            if e.is_empty() || !e.iter().all(u8::is_ascii_digit) {
+                return Err(());
+            }
+            // saturates far beyond the range of f64
+            let e = e.iter().fold(0_i64, |acc, &d| {
+                (acc * 10 + i64::from(d - b'0')).min(1 << 20)
+            });
+            if neg {
+                -e
+            } else {
+                e
+            }
+        }
+    };
+
+    let mut m: u64 = 0;
+    let mut has_digit = false;
+    let mut has_dot = false;
+    for &b in mantissa {
+        if b == b'.' {
+            if has_dot {
+                return Err(());
+            }
+            has_dot = true;
+            continue;
+        }
+        let d = (b as char).to_digit(16).ok_or(())?;
+        has_digit = true;
+        if m >> 60 == 0 {
+            m = m << 4 | u64::from(d);
+            if has_dot {
+                exp -= 4;
+            }
+        } else {
+            // keeps a sticky bit for rounding
+            m |= u64::from(d != 0);
+            if !has_dot {
+                exp += 4;
             }
         }
     }
+    if !has_digit {
+        return Err(());
+    }
+
+    let mut value = m as f64;
+    while exp > 1000 {
+        value *= 2f64.powi(1000);
+        exp -= 1000;
+    }
+    while exp < -1000 {
+        value *= 2f64.powi(-1000);
+        exp += 1000;
+    }
+    Ok(value * 2f64.powi(exp as i32))
 }
 
-fn poll_f64(reader: &mut impl ByteRead) -> Result<Option<f64>, ()> {
-    let mut buf: [u8; 512] = [0; 512];
-    let mut cur: usize = 0;
+/// Required digits after the decimal point.
+///
+/// A literal with an exponent has the required format only with [`Decimals::SameAsStd`],
+/// when the std literal also has an exponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decimals {
+    Exactly(usize),
+    /// The same as the std token
+    SameAsStd,
+}
 
-    let mut byte = reader.next_byte();
-    loop {
-        if byte.is_eof() {
-            return Ok(None);
+/// Significant digits policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigFigs {
+    /// A token also passes if both values are equal after rounding to n significant digits.
+    Round(usize),
+    /// User literals must have at least n significant digits, checked like [`Decimals`].
+    AtLeast(usize),
+}
+
+impl FloatOptions {
+    pub fn new(eps: f64) -> Self {
+        Self {
+            eps,
+            rel_eps: 0.0,
+            max_ulps: None,
+            full_scan: false,
+            mixed: false,
+            decimals: None,
+            decimals_verdict: Comparison::PE,
+            sig_figs: None,
+            literals: LiteralPolicy::new(),
+            lines: false,
+            eps_columns: Vec::new(),
+            period: None,
+            period_columns: Vec::new(),
+            columns: Columns::Line,
+            auto_eps: false,
+            norm: None,
+        }
+    }
+
+    /// Max absolute error of the 0-based `column`, or inferred from the std literal.
+    fn eps_at(&self, column: u64, std_literal: &[u8]) -> f64 {
+        if self.auto_eps {
+            return half_unit(std_literal);
+        }
+        match self.eps_columns.len() {
+            0 => self.eps,
+            n => self.eps_columns[(column % n as u64) as usize],
+        }
+    }
+
+    /// Absolute error of user value `b` to std value `a` at `column`,
+    /// which is the circular distance if the column is periodic.
+    fn distance(&self, column: u64, a: f64, b: f64) -> f64 {
+        let period = match self.period_columns.len() {
+            0 => self.period,
+            n => self.period_columns[(column % n as u64) as usize],
+        };
+        match period {
+            None => (b - a).abs(),
+            Some(p) => {
+                let d = (b - a).rem_euclid(p);
+                d.min(p - d)
+            }
+        }
+    }
+
+    /// Whether user value `b` is close enough to std value `a` at `column`.
+    /// `diff` is the absolute error.
+    fn is_close(&self, column: u64, std_literal: &[u8], a: f64, b: f64, diff: f64) -> bool {
+        within_eps(a, b, diff, self.eps_at(column, std_literal), self.rel_eps)
+            || matches!(self.max_ulps, Some(n) if ulps_between(a, b).is_some_and(|d| d <= n))
+            || (self.literals.nan_eq && a.is_nan() && b.is_nan())
+            || matches!(self.sig_figs, Some(SigFigs::Round(n)) if round_eq(a, b, n))
+    }
+
+    /// Whether the aggregated errors are within `eps` or `rel_eps`.
+    fn is_norm_close(&self, norm: &FloatNorm) -> bool {
+        let within =
+            |x: f64, tol: f64| !matches!(x.partial_cmp(&tol), None | Some(Ordering::Greater));
+        within(norm.abs_error, self.eps)
+            || matches!(norm.rel_error, Some(e) if self.rel_eps > 0.0 && within(e, self.rel_eps))
+    }
+
+    /// Whether the user literal has the required decimal places and significant digits.
+    fn is_well_formatted(&self, std_literal: &[u8], user_literal: &[u8]) -> bool {
+        if let Some(SigFigs::AtLeast(n)) = self.sig_figs {
+            if significant_digits(user_literal).is_some_and(|k| k < n) {
+                return false;
+            }
+        }
+        // "%.nf" never prints an exponent
+        let (required, exponent) = match self.decimals {
+            None => return true,
+            Some(Decimals::Exactly(k)) => (k, false),
+            Some(Decimals::SameAsStd) => (decimal_places(std_literal), has_exponent(std_literal)),
+        };
+        decimal_places(user_literal) == required && has_exponent(user_literal) == exponent
+    }
+}
+
+/// Whether two finite values are equal after rounding to `n` significant digits.
+fn round_eq(a: f64, b: f64, n: usize) -> bool {
+    // exponent formatting rounds the exact binary value
+    let round = |x: f64| format!("{:.*e}", n.max(1) - 1, x);
+    a.is_finite() && b.is_finite() && round(a) == round(b)
+}
+
+/// Half a unit in the last printed digit of a decimal literal, like 0.005 for "3.14".
+/// Returns zero for other literals, which are compared exactly.
+fn half_unit(literal: &[u8]) -> f64 {
+    let unsigned = match literal.first() {
+        Some(b'+') | Some(b'-') => &literal[1..],
+        _ => literal,
+    };
+    if is_hex(literal) || is_nan_inf(unsigned) {
+        return 0.0;
+    }
+    let exp = match literal.iter().position(|&b| b == b'e' || b == b'E') {
+        None => 0,
+        Some(idx) => {
+            let (neg, digits) = match literal.get(idx + 1) {
+                Some(b'-') => (true, &literal[idx + 2..]),
+                Some(b'+') => (false, &literal[idx + 2..]),
+                _ => (false, &literal[idx + 1..]),
+            };
+            // saturates far beyond the range of f64
+            let e = digits.iter().fold(0_i32, |acc, &d| {
+                (acc * 10 + i32::from(d - b'0')).min(1 << 20)
+            });
+            if neg {
+                -e
+            } else {
+                e
+            }
+        }
+    };
+    let places = decimal_places(literal).min(1 << 20) as i32;
+    0.5 * 10f64.powi(exp - places)
+}
+
+/// The literal before any exponent.
+fn mantissa(literal: &[u8]) -> &[u8] {
+    let marker: &[u8] = if is_hex(literal) { b"pP" } else { b"eE" };
+    match literal.iter().position(|b| marker.contains(b)) {
+        Some(idx) => &literal[..idx],
+        None => literal,
+    }
+}
+
+fn has_exponent(literal: &[u8]) -> bool {
+    mantissa(literal).len() != literal.len()
+}
+
+/// Number of digits from the first non-zero digit of the mantissa, including trailing zeros.
+/// Returns `None` if there is no non-zero digit, like zero, NaN and infinities.
+fn significant_digits(literal: &[u8]) -> Option<usize> {
+    let hex = is_hex(literal);
+    let mut mantissa = mantissa(literal);
+    if hex {
+        let idx = mantissa
+            .iter()
+            .position(|b| b.eq_ignore_ascii_case(&b'x'))?;
+        mantissa = &mantissa[idx + 1..];
+    }
+    let is_digit = |b: &u8| {
+        if hex {
+            b.is_ascii_hexdigit()
         } else {
-            let b = byte.as_u8();
-            if !b.is_ascii_whitespace() {
-                buf[cur] = b;
-                cur += 1;
-                byte = reader.next_byte();
-                break;
+            b.is_ascii_digit()
+        }
+    };
+    let start = mantissa.iter().position(|b| is_digit(b) && *b != b'0')?;
+    Some(mantissa[start..].iter().filter(|b| is_digit(b)).count())
+}
+
+/// Number of digits after the decimal point, before any exponent.
+fn decimal_places(literal: &[u8]) -> usize {
+    let mantissa = mantissa(literal);
+    match mantissa.iter().position(|&b| b == b'.' || b == b',') {
+        Some(idx) => mantissa.len() - idx - 1,
+        None => 0,
+    }
+}
+
+/// The first float token pair that fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatMismatch {
+    /// 1-based index of the token pair
+    pub token_index: u64,
+    /// `None` if std has ended.
+    pub std_literal: Option<String>,
+    /// `None` if user has ended.
+    pub user_literal: Option<String>,
+    /// `None` if the std token is missing or not a float.
+    pub std_value: Option<f64>,
+    /// `None` if the user token is missing or not a float.
+    pub user_value: Option<f64>,
+    /// `|user - std|`, or the circular distance in a periodic column, if both values are present.
+    pub abs_error: Option<f64>,
+    /// `|user - std| / |std|`, if both values are present and std is not zero.
+    pub rel_error: Option<f64>,
+    /// The eps inferred from the std literal with [`FloatOptions::auto_eps`].
+    pub inferred_eps: Option<f64>,
+}
+
+/// An error and the 1-based index of the token pair where it occurred.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatError {
+    pub error: f64,
+    pub token_index: u64,
+}
+
+/// Error statistics over the whole output, collected with [`FloatOptions::full_scan`].
+///
+/// The scan stops when either side ends.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FloatStats {
+    /// Token pairs where both sides are floats
+    pub compared_tokens: u64,
+    /// Token pairs which fail, including non-float ones
+    pub failed_tokens: u64,
+    /// `None` if no error is comparable.
+    pub max_abs_error: Option<FloatError>,
+    /// `None` if no error is comparable.
+    pub max_rel_error: Option<FloatError>,
+}
+
+impl FloatStats {
+    fn record(&mut self, token_index: u64, std_value: f64, abs_error: f64) {
+        fn update(max: &mut Option<FloatError>, error: f64, token_index: u64) {
+            if error.is_nan() {
+                return;
+            }
+            match *max {
+                Some(ref m) if m.error >= error => {}
+                _ => *max = Some(FloatError { error, token_index }),
             }
         }
-        byte = reader.next_byte();
+
+        self.compared_tokens += 1;
+        update(&mut self.max_abs_error, abs_error, token_index);
+        if std_value != 0.0 {
+            update(
+                &mut self.max_rel_error,
+                abs_error / std_value.abs(),
+                token_index,
+            );
+        }
     }
+}
 
-    while cur < buf.len() {
-        if byte.is_eof() {
-            break;
+/// Norms of the error vector over all float token pairs, collected with [`FloatOptions::norm`].
+///
+/// The comparison stops early at the first missing or non-float token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatNorm {
+    pub norm: Norm,
+    /// Float token pairs aggregated
+    pub compared_tokens: u64,
+    /// Norm of `user - std`. NaN if any error is NaN.
+    pub abs_error: f64,
+    /// `abs_error` divided by the norm of std, `None` if that is zero.
+    pub rel_error: Option<f64>,
+    /// Norm of std values
+    std_norm: f64,
+    /// The largest error of a single token
+    max_error: f64,
+    /// 1-based index of the token with `max_error`, 0 if none
+    worst_token: u64,
+}
+
+impl FloatNorm {
+    fn new(norm: Norm) -> Self {
+        Self {
+            norm,
+            compared_tokens: 0,
+            abs_error: 0.0,
+            rel_error: None,
+            std_norm: 0.0,
+            max_error: 0.0,
+            worst_token: 0,
+        }
+    }
+
+    fn add(&mut self, token_index: u64, std_value: f64, abs_error: f64) {
+        fn max(a: f64, b: f64) -> f64 {
+            if a.is_nan() || b.is_nan() {
+                f64::NAN
+            } else {
+                a.max(b)
+            }
+        }
+
+        self.compared_tokens += 1;
+        // NaN is the worst error, since it poisons the norm
+        let worse = abs_error > self.max_error || (abs_error.is_nan() && !self.max_error.is_nan());
+        if self.worst_token == 0 || worse {
+            self.max_error = abs_error;
+            self.worst_token = token_index;
+        }
+        match self.norm {
+            // `hypot` does not overflow in the middle
+            Norm::L2 => {
+                self.abs_error = self.abs_error.hypot(abs_error);
+                self.std_norm = self.std_norm.hypot(std_value);
+            }
+            Norm::LInf => {
+                self.abs_error = max(self.abs_error, abs_error);
+                self.std_norm = max(self.std_norm, std_value.abs());
+            }
+        }
+        self.rel_error = match self.std_norm {
+            x if x == 0.0 || !x.is_finite() => None,
+            x => Some(self.abs_error / x),
+        };
+    }
+}
+
+/// A token polled by `poll_f64`.
+struct FloatToken<'a> {
+    /// Empty at EOF. Cut at the length limit of [`poll_token`].
+    literal: &'a [u8],
+    /// Length of the whole token
+    len: usize,
+    /// Bytes of the literal which are still in the reader buffer.
+    /// They must be consumed after the token is used.
+    pending: usize,
+    value: Result<Option<f64>, ()>,
+}
+
+impl FloatToken<'_> {
+    fn literal(&self) -> Option<String> {
+        show_literal(self.literal)
+    }
+
+    /// Whether the token is compared exactly in mixed mode.
+    fn is_exact(&self) -> bool {
+        self.value.is_err() || is_integer(self.literal)
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.value.unwrap_or(None)
+    }
+
+    fn is_eof(&self) -> bool {
+        matches!(self.value, Ok(None))
+    }
+}
+
+/// Number of representable values from `a` to `b`.
+///
+/// +0 and -0 are the same value. An infinity is only equal to itself.
+/// Returns `None` if any of them is NaN.
+fn ulps_between(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    if a.is_infinite() || b.is_infinite() {
+        return if a == b { Some(0) } else { None };
+    }
+
+    // maps floats to integers with the same order, where +0 and -0 are both zero.
+    fn key(x: f64) -> i64 {
+        let bits = x.to_bits() as i64;
+        if bits < 0 {
+            -(bits & i64::MAX)
         } else {
-            let b = byte.as_u8();
-            if b.is_ascii_whitespace() {
-                break;
+            bits
+        }
+    }
+    // both keys are within (-2^63, 2^63), so the difference fits in u64.
+    Some((key(a) as i128 - key(b) as i128).unsigned_abs() as u64)
+}
+
+fn is_integer(literal: &[u8]) -> bool {
+    let digits = match literal.first() {
+        Some(b'+') | Some(b'-') => &literal[1..],
+        _ => literal,
+    };
+    !digits.is_empty() && digits.iter().all(u8::is_ascii_digit)
+}
+
+/// Compares two integer literals by value.
+fn int_eq(a: &[u8], b: &[u8]) -> bool {
+    fn split(literal: &[u8]) -> (bool, &[u8]) {
+        let (neg, digits) = match literal[0] {
+            b'-' => (true, &literal[1..]),
+            b'+' => (false, &literal[1..]),
+            _ => (false, literal),
+        };
+        let start = digits.iter().position(|&d| d != b'0');
+        let digits = &digits[start.unwrap_or(digits.len())..];
+        // -0 == 0
+        (neg && !digits.is_empty(), digits)
+    }
+    is_integer(a) && is_integer(b) && split(a) == split(b)
+}
+
+/// Compares two tokens which are not floats in mixed mode.
+fn exact_eq(std_token: &FloatToken, user_token: &FloatToken) -> bool {
+    let (a, b) = (std_token.literal, user_token.literal);
+    if is_integer(a) {
+        int_eq(a, b)
+    } else {
+        a == b
+    }
+}
+
+fn mismatch_reason(std_token: &FloatToken, user_token: &FloatToken, mixed: bool) -> MismatchReason {
+    match (std_token.value, user_token.value) {
+        (Ok(None), _) => MismatchReason::ExtraOutput,
+        (_, Ok(None)) => MismatchReason::MissingOutput,
+        (Err(()), _) if mixed => MismatchReason::TokenMismatch,
+        (Err(()), _) | (_, Err(())) => MismatchReason::InvalidToken,
+        _ => MismatchReason::TokenMismatch,
+    }
+}
+
+/// Whether user value `b` is close enough to std value `a`, where `diff` is the absolute error:
+/// within `eps`, or within `rel_eps` relative to std. Zero `rel_eps` disables the relative check.
+#[inline]
+pub(super) fn within_eps(a: f64, b: f64, diff: f64, eps: f64, rel_eps: f64) -> bool {
+    // equal infinities are close
+    a == b || diff <= eps || (rel_eps > 0.0 && a.is_finite() && diff <= rel_eps * a.abs())
+}
+
+impl FloatMismatch {
+    /// Describes a failing pair of `(literal, value)`, where `abs_error` is present if both
+    /// values are.
+    pub(super) fn new(
+        token_index: u64,
+        std: (Option<String>, Option<f64>),
+        user: (Option<String>, Option<f64>),
+        abs_error: Option<f64>,
+    ) -> Self {
+        let rel_error = match (std.1, abs_error) {
+            (Some(a), Some(diff)) if a != 0.0 => Some(diff / a.abs()),
+            _ => None,
+        };
+        Self {
+            token_index,
+            std_literal: std.0,
+            user_literal: user.0,
+            std_value: std.1,
+            user_value: user.1,
+            abs_error,
+            rel_error,
+            inferred_eps: None,
+        }
+    }
+}
+
+fn float_mismatch(
+    options: &FloatOptions,
+    token_index: u64,
+    column: u64,
+    std_token: &FloatToken,
+    user_token: &FloatToken,
+) -> FloatMismatch {
+    let std_value = std_token.value();
+    let user_value = user_token.value();
+    let abs_error = match (std_value, user_value) {
+        (Some(a), Some(b)) => Some(options.distance(column, a, b)),
+        _ => None,
+    };
+    let mut mismatch = FloatMismatch::new(
+        token_index,
+        (std_token.literal(), std_value),
+        (user_token.literal(), user_value),
+        abs_error,
+    );
+    if std_value.is_some() && options.auto_eps {
+        mismatch.inferred_eps = Some(half_unit(std_token.literal));
+    }
+    mismatch
+}
+
+/// Whitespaces before a token, with trailing whitespaces of each line trimmed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct Separator {
+    line_breaks: u64,
+    /// Whitespaces after the last line break
+    indent: Vec<u8>,
+}
+
+impl Separator {
+    fn clear(&mut self) {
+        self.line_breaks = 0;
+        self.indent.clear();
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            None => self.indent.extend_from_slice(bytes),
+            Some(idx) => {
+                self.line_breaks += bytes.iter().filter(|&&b| b == b'\n').count() as u64;
+                self.indent.clear();
+                self.indent.extend_from_slice(&bytes[idx + 1..]);
             }
-            buf[cur] = b;
-            cur += 1;
         }
-        byte = reader.next_byte();
     }
+}
 
-    if cur >= buf.len() {
-        return Err(());
+/// Compares a token pair which is not at the end of both sides.
+///
+/// Returns the verdict and the reason if the pair fails.
+fn compare_tokens(
+    options: &FloatOptions,
+    token_index: u64,
+    column: u64,
+    std_token: &FloatToken,
+    user_token: &FloatToken,
+    stats: &mut FloatStats,
+    norm: Option<&mut FloatNorm>,
+) -> Option<(Comparison, MismatchReason)> {
+    let (ok, is_float) = match (std_token.value, user_token.value) {
+        (Ok(None), _) | (_, Ok(None)) => (false, false),
+        _ if options.mixed && std_token.is_exact() => (exact_eq(std_token, user_token), false),
+        (Ok(Some(a)), Ok(Some(b))) => {
+            let diff = options.distance(column, a, b);
+            if options.full_scan {
+                stats.record(token_index, a, diff);
+            }
+            match norm {
+                Some(norm) => {
+                    // equal infinities have no error
+                    let same = a == b || (options.literals.nan_eq && a.is_nan() && b.is_nan());
+                    norm.add(token_index, a, if same { 0.0 } else { diff });
+                    (true, true)
+                }
+                None => (
+                    options.is_close(column, std_token.literal, a, b, diff),
+                    true,
+                ),
+            }
+        }
+        _ => (false, false),
+    };
+
+    if !ok {
+        let reason = mismatch_reason(std_token, user_token, options.mixed);
+        return Some((Comparison::WA, reason));
+    }
+    if is_float && !options.is_well_formatted(std_token.literal, user_token.literal) {
+        return Some((options.decimals_verdict, MismatchReason::FormatMismatch));
+    }
+    None
+}
+
+fn float_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FloatOptions,
+) -> CompareReport {
+    let mut std_spill: Vec<u8> = Vec::new();
+    let mut user_spill: Vec<u8> = Vec::new();
+    let mut token_index: u64 = 0;
+
+    let mut failure: Option<(Mismatch, FloatMismatch)> = None;
+    // the first badly formatted token which gives PE
+    let mut format_failure: Option<(Mismatch, FloatMismatch)> = None;
+    let mut stats = FloatStats::default();
+    let mut norm = options.norm.map(FloatNorm::new);
+    // the token with the largest error, to blame if the norm fails
+    let mut worst: Option<(Mismatch, FloatMismatch)> = None;
+
+    // std line breaks are also needed by per line columns
+    let line_columns = (!options.eps_columns.is_empty() || !options.period_columns.is_empty())
+        && options.columns == Columns::Line;
+    let mut std_sep = (options.lines || line_columns).then(Separator::default);
+    let mut user_sep = options.lines.then(Separator::default);
+    // 0-based column of the current std token
+    let mut column: u64 = 0;
+    // whether a pair within eps passes with nothing else to check or record
+    let plain_eps = !options.full_scan
+        && norm.is_none()
+        && !options.mixed
+        && !options.lines
+        && options.decimals.is_none()
+        && options.sig_figs.is_none()
+        && !options.auto_eps
+        && options.eps_columns.is_empty()
+        && options.period.is_none()
+        && options.period_columns.is_empty();
+
+    loop {
+        let std_token = poll_f64(
+            std_reader,
+            &mut std_spill,
+            std_sep.as_mut(),
+            &options.literals,
+            usize::MAX,
+        );
+        let user_token = poll_f64(
+            user_reader,
+            &mut user_spill,
+            user_sep.as_mut(),
+            &options.literals,
+            std_token.len.saturating_add(MAX_EXTRA_LEN),
+        );
+        token_index += 1;
+
+        if plain_eps {
+            if let (Ok(Some(a)), Ok(Some(b))) = (std_token.value, user_token.value) {
+                if within_eps(a, b, (b - a).abs(), options.eps, options.rel_eps) {
+                    let (std_pending, user_pending) = (std_token.pending, user_token.pending);
+                    std_reader.consume(std_pending);
+                    user_reader.consume(user_pending);
+                    continue;
+                }
+            }
+        }
+
+        let ended = std_token.is_eof() || user_token.is_eof();
+        if std_token.is_eof() && user_token.is_eof() {
+            break;
+        }
+
+        column = match (options.columns, &std_sep) {
+            (Columns::Line, Some(sep)) if sep.line_breaks > 0 => 0,
+            (Columns::Line, _) if token_index > 1 => column + 1,
+            (Columns::Line, _) => 0,
+            (Columns::Token, _) => token_index - 1,
+        };
+
+        let worst_token = norm.as_ref().map(|norm| norm.worst_token);
+        let mut verdict = compare_tokens(
+            options,
+            token_index,
+            column,
+            &std_token,
+            &user_token,
+            &mut stats,
+            norm.as_mut(),
+        );
+        if let (None, Some(a), Some(b)) = (verdict, &std_sep, &user_sep) {
+            if !ended && a != b {
+                let reason = if a.line_breaks != b.line_breaks {
+                    MismatchReason::LineBreakMismatch
+                } else {
+                    MismatchReason::WhitespaceOnly
+                };
+                verdict = Some((Comparison::PE, reason));
+            }
+        }
+        // only the first failure of each verdict is reported
+        let slot = match verdict {
+            Some((Comparison::PE, reason)) if format_failure.is_none() => {
+                Some((&mut format_failure, reason))
+            }
+            Some((Comparison::PE, _)) => None,
+            Some((_, reason)) if failure.is_none() => Some((&mut failure, reason)),
+            _ => None,
+        };
+        let is_worst = norm.as_ref().map(|norm| norm.worst_token) != worst_token;
+        let details = (slot.is_some() || is_worst)
+            .then(|| float_mismatch(options, token_index, column, &std_token, &user_token));
+
+        let (std_len, user_len) = (std_token.len, user_token.len);
+        let (std_pending, user_pending) = (std_token.pending, user_token.pending);
+        std_reader.consume(std_pending);
+        user_reader.consume(user_pending);
+
+        if let Some(float_mismatch) = details {
+            let (std_pos, user_pos) = (
+                token_position(std_reader, std_len),
+                token_position(user_reader, user_len),
+            );
+            let mismatch = |reason| Mismatch {
+                std: std_pos,
+                user: user_pos,
+                reason,
+            };
+            if is_worst {
+                worst = Some((
+                    mismatch(MismatchReason::TokenMismatch),
+                    float_mismatch.clone(),
+                ));
+            }
+            if let Some((slot, reason)) = slot {
+                *slot = Some((mismatch(reason), float_mismatch));
+            }
+        }
+
+        match verdict {
+            None | Some((Comparison::PE, _)) => {}
+            Some(_) => {
+                stats.failed_tokens += 1;
+                if !options.full_scan || ended {
+                    break;
+                }
+            }
+        }
+    }
+
+    let std_consumed = std_reader.position().offset;
+    let user_consumed = user_reader.position().offset;
+    let failure = match (failure, format_failure) {
+        (Some(failure), _) => Some((Comparison::WA, failure)),
+        (None, Some(failure)) => Some((Comparison::PE, failure)),
+        (None, None) => None,
+    };
+    let norm_failed = match norm {
+        Some(ref norm) => !options.is_norm_close(norm),
+        None => false,
+    };
+    let failure = match failure {
+        // the token with the largest error is to blame if only the norm fails
+        Some((Comparison::PE, _)) | None if norm_failed => {
+            worst.map(|worst| (Comparison::WA, worst))
+        }
+        failure => failure,
+    };
+    let mut report = match failure {
+        None if norm_failed => {
+            CompareReport::new(Comparison::WA, None, std_consumed, user_consumed)
+        }
+        None => CompareReport::new(Comparison::AC, None, std_consumed, user_consumed),
+        Some((comparison, (mismatch, float_mismatch))) => {
+            let mut report =
+                CompareReport::new(comparison, Some(mismatch), std_consumed, user_consumed);
+            report.float_mismatch = Some(float_mismatch);
+            report
+        }
+    };
+    if options.full_scan {
+        report.float_stats = Some(stats);
+    }
+    report.float_norm = norm;
+    report
+}
+
+fn fill_buf(reader: &mut impl TrackedRead) -> &[u8] {
+    match reader.fill_buf() {
+        Ok(buf) => buf,
+        Err(e) => panic_any(e),
+    }
+}
+
+/// A token separated by ascii whitespaces.
+pub(super) struct RawToken<'a> {
+    /// Empty at EOF. Cut at `max_len` of [`poll_token`].
+    pub(super) literal: &'a [u8],
+    /// Length of the whole token
+    pub(super) len: usize,
+    /// Bytes of the literal which are still in the reader buffer.
+    /// They must be consumed after the token is used.
+    pub(super) pending: usize,
+}
+
+impl RawToken<'_> {
+    /// Returns `None` at EOF.
+    pub(super) fn literal(&self) -> Option<String> {
+        show_literal(self.literal)
+    }
+
+    /// Whether the literal is cut.
+    pub(super) fn is_overlong(&self) -> bool {
+        self.len > self.literal.len()
+    }
+}
+
+/// A user token which is this many bytes longer than the std token fails,
+/// so it is not buffered any further.
+pub(super) const MAX_EXTRA_LEN: usize = 1024;
+
+/// Literals in diagnostics are cut to this many bytes.
+const MAX_SHOWN_LEN: usize = 256;
+
+/// The literal for diagnostics, with "..." if it is cut. Returns `None` if it is empty.
+pub(super) fn show_literal(literal: &[u8]) -> Option<String> {
+    if literal.is_empty() {
+        return None;
+    }
+    if literal.len() <= MAX_SHOWN_LEN {
+        return Some(String::from_utf8_lossy(literal).into_owned());
+    }
+    let mut s = String::from_utf8_lossy(&literal[..MAX_SHOWN_LEN]).into_owned();
+    s.push_str("...");
+    Some(s)
+}
+
+/// Polls the next token.
+///
+/// The literal is borrowed from the reader buffer if it is contiguous there,
+/// or copied into `spill` if it crosses refills.
+/// Only the first `max_len` bytes are kept, and the rest of the token is skipped.
+pub(super) fn poll_token<'a>(
+    reader: &'a mut impl TrackedRead,
+    spill: &'a mut Vec<u8>,
+    mut sep: Option<&mut Separator>,
+    max_len: usize,
+) -> RawToken<'a> {
+    if let Some(sep) = sep.as_mut() {
+        sep.clear();
+    }
+    loop {
+        let buf = fill_buf(reader);
+        if buf.is_empty() {
+            return RawToken {
+                literal: &[],
+                len: 0,
+                pending: 0,
+            };
+        }
+        let start = buf.iter().position(|b| !b.is_ascii_whitespace());
+        let skip = start.unwrap_or(buf.len());
+        if let Some(sep) = sep.as_mut() {
+            sep.push(&buf[..skip]);
+        }
+        let len = start.and_then(|start| buf[start..].iter().position(u8::is_ascii_whitespace));
+        reader.consume(skip);
+        if let Some(len) = len {
+            return RawToken {
+                literal: &fill_buf(reader)[..len.min(max_len)],
+                len,
+                pending: len,
+            };
+        }
+        if start.is_some() {
+            break;
+        }
+    }
+
+    spill.clear();
+    let mut len = 0;
+    loop {
+        let buf = fill_buf(reader);
+        if buf.is_empty() {
+            break;
+        }
+        let end = buf.iter().position(u8::is_ascii_whitespace);
+        let chunk = end.unwrap_or(buf.len());
+        let kept = chunk.min(max_len - spill.len());
+        spill.extend_from_slice(&buf[..kept]);
+        reader.consume(chunk);
+        len += chunk;
+        if end.is_some() {
+            break;
+        }
+    }
+    RawToken {
+        literal: spill,
+        len,
+        pending: 0,
     }
-    if cur == 0 {
-        return Ok(None);
+}
+
+/// Returns the position of a token of `len` bytes which has just been consumed.
+///
+/// Positions are only taken on a mismatch, since they are not free.
+pub(super) fn token_position(reader: &impl TrackedRead, len: usize) -> Position {
+    // a token never crosses a line
+    let pos = reader.position();
+    Position {
+        offset: pos.offset.saturating_sub(len as u64),
+        line: pos.line,
+        column: pos.column.saturating_sub(len as u64),
     }
-    match lexical_core::parse::<f64>(&buf[..cur]) {
-        Ok(x) => Ok(Some(x)),
-        Err(_) => Err(()),
+}
+
+fn poll_f64<'a>(
+    reader: &'a mut impl TrackedRead,
+    spill: &'a mut Vec<u8>,
+    sep: Option<&mut Separator>,
+    policy: &LiteralPolicy,
+    max_len: usize,
+) -> FloatToken<'a> {
+    let RawToken {
+        literal,
+        len,
+        pending,
+    } = poll_token(reader, spill, sep, max_len);
+    let value = match literal {
+        [] => Ok(None),
+        // an overlong token is never a float
+        _ if len > literal.len() => Err(()),
+        _ => policy.parse(literal).map(Some),
+    };
+    FloatToken {
+        literal,
+        len,
+        pending,
+        value,
     }
 }
 
@@ -96,13 +1161,15 @@ fn poll_f64(reader: &mut impl ByteRead) -> Result<Option<f64>, ()> {
 fn test_spj_float_comparer() {
     const DEFAULT_EPS: f64 = 1e-10;
 
+    use crate::byte_read::ByteReader;
+
     macro_rules! judge {
         ($ret:expr, $std:expr,$user:expr) => {{
-            let mut std: &[u8] = $std.as_ref();
-            let mut user: &[u8] = $user.as_ref();
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
 
-            let ret = float_compare(&mut std, &mut user, DEFAULT_EPS);
-            assert_eq!(ret, $ret);
+            let ret = float_compare(&mut std, &mut user, &FloatOptions::new(DEFAULT_EPS));
+            assert_eq!(ret.comparison, $ret);
         }};
     }
 
@@ -131,3 +1198,746 @@ fn test_spj_float_comparer() {
     judge!(WA, b"0.0", b"-inf");
     judge!(WA, b"0.0", b"+inf");
 }
+
+#[test]
+fn test_float_max_ulps() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $ulps:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(0.0);
+            options.max_ulps = Some($ulps);
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    // 1.0 + 2^-52, 1.0 + 2^-51
+    judge!(AC, 1, b"1", b"1.0000000000000002");
+    judge!(WA, 1, b"1", b"1.0000000000000004");
+    judge!(AC, 2, b"1", b"1.0000000000000004");
+    // 1.0 - 2^-53
+    judge!(AC, 1, b"1", b"0.99999999999999989");
+    judge!(AC, 0, b"1e300", b"1e300");
+    judge!(
+        AC,
+        4,
+        b"1e300 -1e300",
+        b"1.0000000000000002e300 -1.0000000000000002e300"
+    );
+    judge!(WA, 4, b"1e300", b"1.000000000000001e300");
+
+    // signed zero and subnormals
+    judge!(AC, 0, b"0", b"-0");
+    judge!(AC, 1, b"0", b"4.9406564584124654e-324");
+    judge!(
+        AC,
+        2,
+        b"-4.9406564584124654e-324",
+        b"4.9406564584124654e-324"
+    );
+    judge!(
+        WA,
+        1,
+        b"-4.9406564584124654e-324",
+        b"4.9406564584124654e-324"
+    );
+    judge!(
+        AC,
+        1,
+        b"2.2250738585072014e-308",
+        b"2.2250738585072009e-308"
+    );
+
+    // infinities and nan
+    judge!(AC, 0, b"inf -inf", b"inf -inf");
+    judge!(WA, 1000, b"1.7976931348623157e308", b"inf");
+    judge!(WA, 1000, b"inf", b"-inf");
+    judge!(WA, 1000, b"nan", b"nan");
+    judge!(WA, 1000, b"1", b"nan");
+
+    assert_eq!(ulps_between(0.0, -0.0), Some(0));
+    assert_eq!(
+        ulps_between(f64::MIN, f64::MAX),
+        Some(2 * (f64::MAX.to_bits()))
+    );
+    assert_eq!(ulps_between(f64::INFINITY, f64::INFINITY), Some(0));
+    assert_eq!(ulps_between(f64::NAN, 1.0), None);
+}
+
+#[test]
+fn test_float_decimals() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $decimals:expr, $verdict:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(1e-3);
+            options.decimals = Some($decimals);
+            options.decimals_verdict = $verdict;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+            ret
+        }};
+    }
+
+    use Comparison::*;
+    use Decimals::*;
+
+    judge!(AC, Exactly(3), PE, b"3.100", b"3.100");
+    judge!(AC, Exactly(3), PE, b"3.1", b"3.100");
+    judge!(AC, Exactly(0), PE, b"3", b"3");
+    judge!(PE, Exactly(2), PE, b"100", b"1.00e2");
+    judge!(PE, Exactly(2), PE, b"100", b"100.00e0");
+    judge!(PE, Exactly(3), PE, b"3.100", b"3.1");
+    judge!(PE, Exactly(3), PE, b"3.100", b"3.1000");
+    judge!(PE, Exactly(1), PE, b"3.0", b"3");
+    judge!(WA, Exactly(3), WA, b"3.100", b"3.1");
+    judge!(WA, Exactly(3), PE, b"3.100 1.000", b"3.1 2.000");
+    judge!(WA, Exactly(3), PE, b"3.100", b"3.200");
+
+    judge!(AC, SameAsStd, PE, b"1.5 2.25 3", b"1.5 2.25 3");
+    judge!(PE, SameAsStd, PE, b"1.5 2.25 3", b"1.5 2.250 3");
+    judge!(PE, SameAsStd, PE, b"1.5 2.25 3", b"1.5 2.25 3.0");
+    judge!(AC, SameAsStd, PE, b"1.50e2", b"1.50E+2");
+    judge!(PE, SameAsStd, PE, b"1.50e2", b"150.00");
+    judge!(PE, SameAsStd, PE, b"150.00", b"1.50e2");
+
+    let ret = judge!(PE, Exactly(3), PE, b"1.000 2.000", b"1.000 2.0");
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::FormatMismatch);
+    assert_eq!(mismatch.user.offset, 6);
+    assert_eq!(ret.float_mismatch.unwrap().token_index, 2);
+
+    let ret = judge!(WA, Exactly(3), WA, b"1.000 2.000", b"1.000 2.0");
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::FormatMismatch);
+}
+
+#[test]
+fn test_float_literals() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $policy:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(1e-9);
+            options.literals = $policy;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    macro_rules! policy {
+        ($($field:ident: $value:expr),*) => {{
+            #[allow(unused_mut)]
+            let mut policy = LiteralPolicy::new();
+            $(policy.$field = $value;)*
+            policy
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, policy!(), b"1e3 +2 -inf", b"1000 2.0 -Infinity");
+    judge!(WA, policy!(), b"nan", b"nan");
+    judge!(WA, policy!(), b"16", b"0x10");
+    judge!(WA, policy!(), b"1.5", b"1,5");
+    judge!(WA, policy!(), b"1", b"+-1");
+    judge!(WA, policy!(), b"1", b"--1");
+
+    judge!(AC, policy!(nan_eq: true), b"nan 1", b"NaN 1");
+    judge!(AC, policy!(nan_eq: true), b"-nan", b"nan");
+    judge!(WA, policy!(nan_eq: true), b"nan", b"1");
+    judge!(WA, policy!(nan_inf: false), b"1", b"inf");
+    judge!(WA, policy!(nan_inf: false), b"nan", b"nan");
+    judge!(WA, policy!(exponent: false), b"1000", b"1e3");
+    judge!(AC, policy!(exponent: false), b"1000", b"1000.0");
+    judge!(WA, policy!(plus_sign: false), b"2", b"+2");
+    judge!(AC, policy!(plus_sign: false), b"-2", b"-2");
+
+    judge!(
+        AC,
+        policy!(hex: true),
+        b"16 -0.5 12",
+        b"0x10 -0x1p-1 0x1.8P3"
+    );
+    judge!(AC, policy!(hex: true), b"0.9375", b"0x.F");
+    judge!(WA, policy!(hex: true), b"1", b"0x");
+    judge!(WA, policy!(hex: true), b"1", b"0x1p");
+    judge!(WA, policy!(hex: true), b"1", b"0x1.0.0");
+
+    judge!(AC, policy!(comma: true), b"3.14 2", b"3,14 2");
+    judge!(AC, policy!(comma: true), b"1500", b"1,5e3");
+    judge!(WA, policy!(comma: true), b"1000.5", b"1,000.5");
+
+    let policy = LiteralPolicy::new();
+    assert_eq!(policy.parse(b"-0"), Ok(-0.0));
+    assert_eq!(parse_hex(b"1p1024"), Ok(f64::INFINITY));
+    assert_eq!(parse_hex(b"1p-1074"), Ok(f64::from_bits(1)));
+    assert_eq!(
+        parse_hex(b"123456789abcdef01"),
+        Ok(0x123456789abcdef01_u128 as f64)
+    );
+    assert_eq!(decimal_places(b"0x1.8p3"), 1);
+    assert_eq!(decimal_places(b"3,140"), 3);
+}
+
+#[test]
+fn test_poll_token_max_len() {
+    use crate::byte_read::ByteReader;
+    use std::io::BufRead;
+
+    let input = format!("{} 1", "x".repeat(5000));
+    for &capacity in [3, 64, 8192].iter() {
+        let mut reader = ByteReader::with_capacity(capacity, input.as_bytes());
+        let mut spill = Vec::new();
+        let token = poll_token(&mut reader, &mut spill, None, 10);
+        assert_eq!((token.literal, token.len), (&b"xxxxxxxxxx"[..], 5000));
+        assert!(token.is_overlong());
+        let pending = token.pending;
+        reader.consume(pending);
+        assert!(spill.capacity() < 1024);
+
+        let token = poll_token(&mut reader, &mut spill, None, 10);
+        assert_eq!((token.literal, token.len), (&b"1"[..], 1));
+        assert!(!token.is_overlong());
+    }
+}
+
+#[test]
+fn test_float_long_tokens() {
+    use crate::byte_read::ByteReader;
+
+    let digits = "1234567890".repeat(100);
+    let std = format!("0.{} {}0 {}", digits, digits, digits);
+    let user = format!("0.{}\n{}0 x{}", digits, digits, digits);
+
+    for &capacity in [1, 3, 64, 4096].iter() {
+        let mut std = ByteReader::with_capacity(capacity, std.as_bytes());
+        let mut user = ByteReader::with_capacity(capacity, user.as_bytes());
+        let ret = float_compare(&mut std, &mut user, &FloatOptions::new(1e-6));
+        assert_eq!(ret.comparison, Comparison::WA);
+
+        let mismatch = ret.mismatch.unwrap();
+        assert_eq!(mismatch.reason, MismatchReason::InvalidToken);
+        assert_eq!(mismatch.user.offset, 2005);
+        let m = ret.float_mismatch.unwrap();
+        assert_eq!(m.token_index, 3);
+        // literals are cut in diagnostics
+        assert_eq!(m.std_literal.unwrap(), format!("{}...", &digits[..256]));
+        assert_eq!(m.user_literal.unwrap(), format!("x{}...", &digits[..255]));
+    }
+
+    let mut options = FloatOptions::new(0.0);
+    options.mixed = true;
+    let std = format!("{}1 {}", digits, digits);
+    let user = format!("{}2 {}", digits, digits);
+    let mut std = ByteReader::with_capacity(7, std.as_bytes());
+    let mut user = ByteReader::with_capacity(7, user.as_bytes());
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::WA);
+    assert_eq!(ret.float_mismatch.unwrap().token_index, 1);
+}
+
+#[test]
+fn test_float_lines() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(1e-6);
+            options.lines = true;
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+                let ret = float_compare(&mut std, &mut user, &options);
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"1 2\n3 4\n", b"1 2\n3 4\n");
+    judge!(AC, b"1 2\n3 4\n", b"1.0000001 2 \r\n3 4");
+    judge!(AC, b"1 2\n3 4", b"1 2\n3 4\n\n\n");
+    judge!(PE, b"1 2\n3 4\n", b"1 2 3 4\n");
+    judge!(PE, b"1 2\n3 4\n", b"1\n2\n3\n4\n");
+    judge!(PE, b"1 2\n3 4\n", b"1  2\n3 4\n");
+    judge!(PE, b"1 2\n3 4\n", b"1 2\n\n3 4\n");
+    judge!(PE, b"1 2\n3 4\n", b"1 2\n 3 4\n");
+    judge!(WA, b"1 2\n3 4\n", b"1 2 3 5\n");
+    judge!(WA, b"1 2\n3 4\n", b"1 2\n3\n");
+
+    let mut options = FloatOptions::new(1e-6);
+    options.lines = true;
+    let mut std = ByteReader::with_capacity(64, &b"1 2\n3 4"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1 2 3\t4"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::LineBreakMismatch);
+    assert_eq!((mismatch.std.line, mismatch.user.line), (2, 1));
+    assert_eq!(ret.float_mismatch.unwrap().token_index, 3);
+}
+
+#[test]
+fn test_float_eps_columns() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $columns:ident, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(0.0);
+            options.eps_columns = vec![1e-6, 1e-6, 1e-3];
+            options.columns = Columns::$columns;
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+                let ret = float_compare(&mut std, &mut user, &options);
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(
+        AC,
+        Line,
+        b"1 2 90\n3 4 180\n",
+        b"1 2 90.0005\n3 4 179.9995\n"
+    );
+    judge!(WA, Line, b"1 2 90\n3 4 180\n", b"1 2.0005 90\n3 4 180\n");
+    judge!(WA, Line, b"1 2 90\n3 4 180\n", b"1 2 90\n3.0005 4 180\n");
+    // cycles within a line
+    judge!(AC, Line, b"1 2 90 4 5 180", b"1 2 90.0005 4 5 180.0005");
+    judge!(WA, Line, b"1 2 90 4 5 180", b"1 2 90.0005 4.0005 5 180");
+    // columns follow std lines, not user lines
+    judge!(
+        AC,
+        Line,
+        b"1 2 90\n3 4 180\n",
+        b"1 2\n90.0005 3 4\n179.9995"
+    );
+
+    judge!(
+        AC,
+        Token,
+        b"1 2 90\n3 4 180\n",
+        b"1 2 90.0005\n3 4 180.0005\n"
+    );
+    judge!(WA, Token, b"1 2\n90 3\n", b"1 2\n90 3.0005\n");
+    judge!(AC, Token, b"1 2\n90 3\n", b"1 2\n90.0005 3\n");
+}
+
+#[test]
+fn test_float_report() {
+    use crate::byte_read::ByteReader;
+
+    let mut std = ByteReader::with_capacity(4, &b"1.0 2.0\n3.0"[..]);
+    let mut user = ByteReader::with_capacity(4, &b"1.0\n2.0\n3.5"[..]);
+    let ret = float_compare(&mut std, &mut user, &FloatOptions::new(1e-6));
+    assert_eq!(ret.comparison, Comparison::WA);
+
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!((mismatch.std.offset, mismatch.std.line), (8, 2));
+    assert_eq!((mismatch.user.offset, mismatch.user.line), (8, 3));
+    assert_eq!((mismatch.std.column, mismatch.user.column), (1, 1));
+}
+
+#[test]
+fn test_float_mismatch() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($std:expr, $user:expr) => {{
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &FloatOptions::new(1e-6));
+            assert_eq!(ret.comparison, Comparison::WA);
+            ret.float_mismatch.unwrap()
+        }};
+    }
+
+    let m = judge!(b"1 2 4", b"1 2 3");
+    assert_eq!(m.token_index, 3);
+    assert_eq!(m.std_literal.as_deref(), Some("4"));
+    assert_eq!(m.user_literal.as_deref(), Some("3"));
+    assert_eq!((m.std_value, m.user_value), (Some(4.0), Some(3.0)));
+    assert_eq!((m.abs_error, m.rel_error), (Some(1.0), Some(0.25)));
+
+    let m = judge!(b"0 1", b"0");
+    assert_eq!(m.token_index, 2);
+    assert_eq!(m.user_literal, None);
+    assert_eq!((m.std_value, m.user_value), (Some(1.0), None));
+    assert_eq!(m.abs_error, None);
+
+    let m = judge!(b"0", b"0.5");
+    assert_eq!(m.abs_error, Some(0.5));
+    assert_eq!(m.rel_error, None);
+
+    let m = judge!(b"1", b"x1");
+    assert_eq!(m.user_literal.as_deref(), Some("x1"));
+    assert_eq!(m.user_value, None);
+
+    let m = judge!(b"1", b"nan");
+    assert!(m.user_value.unwrap().is_nan());
+
+    let reason_of = |std: &[u8], user: &[u8]| {
+        let mut std = ByteReader::with_capacity(64, std);
+        let mut user = ByteReader::with_capacity(64, user);
+        let ret = float_compare(&mut std, &mut user, &FloatOptions::new(1e-6));
+        ret.mismatch.unwrap().reason
+    };
+    assert_eq!(reason_of(b"1", b"1 2"), MismatchReason::ExtraOutput);
+    assert_eq!(reason_of(b"1 2", b"1"), MismatchReason::MissingOutput);
+    assert_eq!(reason_of(b"1 2", b"1 x"), MismatchReason::InvalidToken);
+    assert_eq!(reason_of(b"1 2", b"1 3"), MismatchReason::TokenMismatch);
+}
+
+#[test]
+fn test_float_stats() {
+    use crate::byte_read::ByteReader;
+
+    let mut options = FloatOptions::new(0.1);
+    options.full_scan = true;
+
+    let mut std = ByteReader::with_capacity(64, &b"1 2 4 8 16 x"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1 2.5 4 7 16.05 y 0"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::WA);
+    assert_eq!(ret.float_mismatch.unwrap().token_index, 2);
+
+    let stats = ret.float_stats.unwrap();
+    assert_eq!(stats.compared_tokens, 5);
+    assert_eq!(stats.failed_tokens, 4);
+    let max_abs = stats.max_abs_error.unwrap();
+    assert_eq!((max_abs.error, max_abs.token_index), (1.0, 4));
+    let max_rel = stats.max_rel_error.unwrap();
+    assert_eq!((max_rel.error, max_rel.token_index), (0.25, 2));
+
+    let mut std = ByteReader::with_capacity(64, &b"1 2"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1 2.05"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::AC);
+    let stats = ret.float_stats.unwrap();
+    assert_eq!((stats.compared_tokens, stats.failed_tokens), (2, 0));
+}
+
+#[test]
+fn test_float_rel_eps() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $eps:expr, $rel_eps:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new($eps);
+            options.rel_eps = $rel_eps;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, 0.0, 1e-6, b"1e12", b"1000000000100");
+    judge!(WA, 0.0, 1e-6, b"1e12", b"1000001000100");
+    judge!(WA, 1e-6, 0.0, b"1e12", b"1000000000100");
+    judge!(WA, 0.0, 1e-6, b"0", b"1e-9");
+    judge!(AC, 1e-6, 1e-6, b"0 1e12", b"1e-9 1000000000100");
+    judge!(AC, 1e-6, 1e-6, b"-1e12", b"-1000000000100");
+    judge!(WA, 1e-6, 1e-6, b"inf", b"1e300");
+    judge!(AC, 1e-6, 1e-6, b"inf", b"inf");
+}
+
+#[test]
+fn test_float_mixed() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(1e-6);
+            options.mixed = true;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"Case 1: 3.14159265", b"Case 1: 3.1415926");
+    judge!(AC, b"Case #2: -0 007", b"Case #2: 0 +7");
+    judge!(AC, b"x 1.5", b"x 1.5000001");
+    judge!(AC, b"1.0", b"1");
+    judge!(WA, b"Case 1: 3.14", b"case 1: 3.14");
+    judge!(WA, b"Case 1: 3.14", b"Case 1 3.14");
+    judge!(WA, b"1", b"1.0");
+    judge!(WA, b"12345678901234567890", b"12345678901234567891");
+    judge!(WA, b"1.0", b"one");
+    judge!(WA, b"yes", b"yes no");
+
+    let mut options = FloatOptions::new(1e-6);
+    options.mixed = true;
+    let reason_of = |std: &[u8], user: &[u8]| {
+        let mut std = ByteReader::with_capacity(64, std);
+        let mut user = ByteReader::with_capacity(64, user);
+        let ret = float_compare(&mut std, &mut user, &options);
+        ret.mismatch.unwrap().reason
+    };
+    assert_eq!(
+        reason_of(b"Case 1", b"case 1"),
+        MismatchReason::TokenMismatch
+    );
+    assert_eq!(reason_of(b"1", b"2"), MismatchReason::TokenMismatch);
+    assert_eq!(reason_of(b"1.5", b"x"), MismatchReason::InvalidToken);
+}
+
+#[test]
+fn test_float_norm() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $norm:expr, $eps:expr, $rel_eps:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new($eps);
+            options.rel_eps = $rel_eps;
+            options.norm = Some($norm);
+
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+                let ret = float_compare(&mut std, &mut user, &options);
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+    use Norm::*;
+
+    judge!(AC, L2, 0.0, 0.0, b"", b"");
+    judge!(AC, L2, 0.5, 0.0, b"1 2 3", b"1.3 2 3.4");
+    judge!(WA, L2, 0.49, 0.0, b"1 2 3", b"1.3 2 3.4");
+    judge!(AC, LInf, 0.4, 0.0, b"1 2 3", b"1.3 2 3.4");
+    judge!(WA, LInf, 0.3, 0.0, b"1 2 3", b"1.3 2 3.4");
+    // one token may exceed eps if the norm is within it
+    judge!(AC, L2, 0.15, 0.0, b"0 0", b"0.1 0.1");
+    judge!(WA, L2, 0.1, 0.0, b"0 0", b"0.1 0.1");
+    judge!(AC, L2, 0.0, 1e-3, b"3e9 4e9", b"3.003e9 4.004e9");
+    judge!(WA, L2, 0.0, 1e-4, b"3e9 4e9", b"3.003e9 4.004e9");
+    judge!(AC, L2, 0.0, 0.0, b"inf 1e308 1e308", b"inf 1e308 1e308");
+    judge!(WA, L2, 1.0, 0.0, b"1 2", b"1 nan");
+    judge!(WA, LInf, 1.0, 0.0, b"1 2", b"nan 2");
+    judge!(WA, L2, 1.0, 0.0, b"1 2", b"1");
+    judge!(WA, L2, 1.0, 0.0, b"1 2", b"1 x");
+}
+
+#[test]
+fn test_float_norm_report() {
+    use crate::byte_read::ByteReader;
+
+    let mut options = FloatOptions::new(1.0);
+    options.norm = Some(Norm::L2);
+
+    let mut std = ByteReader::with_capacity(64, &b"3 4 0"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"6 8 0"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::WA);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.offset, mismatch.user.offset), (2, 2));
+    let m = ret.float_mismatch.unwrap();
+    assert_eq!(m.token_index, 2);
+    assert_eq!(m.abs_error, Some(4.0));
+    let norm = ret.float_norm.unwrap();
+    assert_eq!(norm.compared_tokens, 3);
+    assert_eq!(norm.abs_error, 5.0);
+    assert_eq!(norm.rel_error, Some(1.0));
+
+    let mut std = ByteReader::with_capacity(64, &b"1 2"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1 2 3"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::WA);
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::ExtraOutput);
+    assert_eq!(ret.float_norm.unwrap().compared_tokens, 2);
+
+    // per token options still apply
+    let mut periodic = FloatOptions::new(0.5);
+    periodic.norm = Some(Norm::L2);
+    periodic.period = Some(360.0);
+    periodic.literals.nan_eq = true;
+    let mut std = ByteReader::with_capacity(64, &b"359.9 nan"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"0.1 nan"[..]);
+    let ret = float_compare(&mut std, &mut user, &periodic);
+    assert_eq!(ret.comparison, Comparison::AC);
+
+    options.decimals = Some(Decimals::Exactly(1));
+    let mut std = ByteReader::with_capacity(64, &b"0"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"0.50"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::PE);
+    let mut std = ByteReader::with_capacity(64, &b"0"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"2.00"[..]);
+    let ret = float_compare(&mut std, &mut user, &options);
+    assert_eq!(ret.comparison, Comparison::WA);
+}
+
+#[test]
+fn test_float_sig_figs() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $eps:expr, $sig_figs:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new($eps);
+            options.sig_figs = Some($sig_figs);
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+    use SigFigs::*;
+
+    judge!(AC, 0.0, Round(6), b"3.14159265", b"3.14159");
+    judge!(AC, 0.0, Round(6), b"3.14159265", b"3.141592");
+    judge!(WA, 0.0, Round(6), b"3.14159265", b"3.14158");
+    judge!(AC, 0.0, Round(3), b"6.02214076e23", b"6.02e23");
+    judge!(AC, 0.0, Round(3), b"-0.000123456", b"-1.23e-4");
+    judge!(WA, 0.0, Round(3), b"-0.000123456", b"1.23e-4");
+    judge!(AC, 0.0, Round(2), b"99.6", b"100");
+    judge!(WA, 0.0, Round(2), b"99.6", b"99");
+    judge!(AC, 0.0, Round(6), b"0 inf", b"-0 inf");
+    judge!(WA, 0.0, Round(6), b"nan", b"nan");
+    judge!(WA, 0.0, Round(6), b"inf", b"1e308");
+    judge!(AC, 1e-3, Round(9), b"1.5", b"1.5004");
+
+    judge!(AC, 1e-3, AtLeast(4), b"3.14159", b"3.142");
+    judge!(AC, 1e-3, AtLeast(4), b"3.14159", b"3.14159");
+    judge!(PE, 1e-3, AtLeast(4), b"3.14", b"3.14");
+    judge!(AC, 1e-3, AtLeast(4), b"0.001", b"0.001000");
+    judge!(PE, 1e-3, AtLeast(4), b"0.001", b"0.00100");
+    judge!(AC, 1e-3, AtLeast(4), b"0", b"0.0");
+    judge!(AC, 1e-3, AtLeast(3), b"1200", b"1.20e3");
+    judge!(WA, 1e-3, AtLeast(4), b"3.14159", b"3.2");
+
+    let sig = |s: &[u8]| significant_digits(s);
+    assert_eq!(sig(b"-001.2300e-5"), Some(5));
+    assert_eq!(sig(b"0.000"), None);
+    assert_eq!(sig(b"inf"), None);
+    assert_eq!(sig(b"0x1.80p3"), Some(3));
+}
+
+#[test]
+fn test_float_auto_eps() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(0.0);
+            options.auto_eps = true;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+            ret
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"0.33 2", b"0.3333333 2.4");
+    judge!(WA, b"0.33 2", b"0.3333333 2.6");
+    judge!(AC, b"3.14159", b"3.1415926");
+    judge!(WA, b"3.14159", b"3.1416");
+    judge!(AC, b"1.5e3", b"1540");
+    judge!(WA, b"1.5e3", b"1560");
+    judge!(AC, b"2.50E-2", b"0.02504");
+    judge!(AC, b"inf", b"inf");
+    judge!(WA, b"nan", b"nan");
+
+    let ret = judge!(WA, b"1 0.125", b"1 0.13");
+    let m = ret.float_mismatch.unwrap();
+    assert_eq!(m.token_index, 2);
+    assert_eq!(m.inferred_eps, Some(0.0005));
+
+    assert_eq!(half_unit(b"42"), 0.5);
+    assert_eq!(half_unit(b"-1.000"), 0.0005);
+    assert_eq!(half_unit(b"1.2e+2"), 5.0);
+    assert_eq!(half_unit(b"0x1.8p3"), 0.0);
+}
+
+#[test]
+fn test_float_period() {
+    use crate::byte_read::ByteReader;
+    use std::f64::consts::TAU;
+
+    macro_rules! judge {
+        ($ret:expr, $period:expr, $period_columns:expr, $std:expr, $user:expr) => {{
+            let mut options = FloatOptions::new(1e-6);
+            options.period = $period;
+            options.period_columns = $period_columns;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = float_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+            ret
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(
+        AC,
+        Some(TAU),
+        vec![],
+        b"3.141592653589793",
+        b"-3.141592653589793"
+    );
+    judge!(AC, Some(TAU), vec![], b"0", b"6.2831849");
+    judge!(AC, Some(TAU), vec![], b"0.0000001", b"6.2831853");
+    judge!(WA, Some(TAU), vec![], b"0", b"3.14");
+    judge!(AC, Some(360.0), vec![], b"-90 180", b"270 -180");
+    judge!(AC, Some(360.0), vec![], b"10", b"730");
+    judge!(WA, Some(360.0), vec![], b"10", b"10.1");
+    judge!(WA, None, vec![], b"-90", b"270");
+    judge!(WA, Some(360.0), vec![], b"inf", b"0");
+    judge!(AC, Some(360.0), vec![], b"inf", b"inf");
+
+    let columns = vec![None, None, Some(360.0)];
+    judge!(
+        AC,
+        None,
+        columns.clone(),
+        b"1 2 -90\n3 4 0\n",
+        b"1 2 270\n3 4 360\n"
+    );
+    judge!(WA, None, columns.clone(), b"1 2 -90\n", b"361 2 -90\n");
+    judge!(
+        WA,
+        None,
+        columns.clone(),
+        b"1 2 -90\n-90 4 0\n",
+        b"1 2 270\n270 4 0\n"
+    );
+
+    let ret = judge!(WA, Some(360.0), vec![], b"0", b"350");
+    let m = ret.float_mismatch.unwrap();
+    assert_eq!(m.abs_error, Some(10.0));
+}
@@ -0,0 +1,344 @@
+use super::float::{poll_token, token_position, MAX_EXTRA_LEN};
+use super::{catch_io, CompareError, CompareReport, Comparison, Mismatch, MismatchReason};
+
+use crate::byte_read::TrackedRead;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+
+use std::panic::AssertUnwindSafe;
+
+pub fn try_fraction_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FractionOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        fraction_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)
+}
+
+/// Options of fraction mode.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FractionOptions {
+    /// Requires user fractions in lowest terms, where `gcd(p, q) = 1`.
+    pub reduced: bool,
+}
+
+impl FractionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The first fraction token pair that differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FractionMismatch {
+    /// 1-based index of the token pair
+    pub token_index: u64,
+    /// `None` if std has ended.
+    pub std_literal: Option<String>,
+    /// `None` if user has ended.
+    pub user_literal: Option<String>,
+}
+
+/// An exact rational number `num / den`.
+///
+/// Accepts `[+-]?[0-9]+(/[0-9]+)?` with a non-zero denominator.
+#[derive(Debug)]
+struct Fraction {
+    num: BigInt,
+    /// Always positive
+    den: BigInt,
+}
+
+impl Fraction {
+    fn parse(literal: &[u8]) -> Option<Self> {
+        let (neg, rest) = match literal.first() {
+            Some(b'-') => (true, &literal[1..]),
+            Some(b'+') => (false, &literal[1..]),
+            _ => (false, literal),
+        };
+        let (num, den) = match rest.iter().position(|&b| b == b'/') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+
+        // `BigInt::parse_bytes` also accepts signs and underscores
+        let parse = |digits: &[u8]| {
+            if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            BigInt::parse_bytes(digits, 10)
+        };
+
+        let num = parse(num)?;
+        let den = match den {
+            None => BigInt::from(1),
+            Some(den) => parse(den)?,
+        };
+        if den == BigInt::from(0) {
+            return None;
+        }
+        Some(Self {
+            num: if neg { -num } else { num },
+            den,
+        })
+    }
+
+    fn is_reduced(&self) -> bool {
+        self.num.gcd(&self.den) == BigInt::from(1)
+    }
+
+    fn value_eq(&self, other: &Self) -> bool {
+        &self.num * &other.den == &other.num * &self.den
+    }
+}
+
+/// User numerators and denominators may have this many more digits than std ones,
+/// like "2/4" for "1/2". Longer ones fail before any big number arithmetic.
+const MAX_EXTRA_DIGITS: usize = 64;
+
+/// Digits of the numerator and the denominator, without leading zeros.
+fn digit_lens(literal: &[u8]) -> (usize, usize) {
+    let unsigned = match literal.first() {
+        Some(b'+') | Some(b'-') => &literal[1..],
+        _ => literal,
+    };
+    let len = |part: &[u8]| part.len() - part.iter().take_while(|&&b| b == b'0').count();
+    match unsigned.iter().position(|&b| b == b'/') {
+        Some(idx) => (len(&unsigned[..idx]), len(&unsigned[idx + 1..])),
+        None => (len(unsigned), 0),
+    }
+}
+
+/// Compares a token pair. Returns `None` if it passes.
+fn compare_tokens(options: &FractionOptions, std: &[u8], user: &[u8]) -> Option<MismatchReason> {
+    match (std, user) {
+        ([], []) => None,
+        ([], _) => Some(MismatchReason::ExtraOutput),
+        (_, []) => Some(MismatchReason::MissingOutput),
+        _ => {
+            let a = match Fraction::parse(std) {
+                Some(a) => a,
+                None => return Some(MismatchReason::InvalidToken),
+            };
+            let (std_num, std_den) = digit_lens(std);
+            let (user_num, user_den) = digit_lens(user);
+            if user_num > std_num + MAX_EXTRA_DIGITS || user_den > std_den + MAX_EXTRA_DIGITS {
+                return Some(MismatchReason::TokenMismatch);
+            }
+            match Fraction::parse(user) {
+                Some(b) if !a.value_eq(&b) => Some(MismatchReason::TokenMismatch),
+                Some(b) if options.reduced && !b.is_reduced() => {
+                    Some(MismatchReason::FormatMismatch)
+                }
+                Some(_) => None,
+                None => Some(MismatchReason::InvalidToken),
+            }
+        }
+    }
+}
+
+fn fraction_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FractionOptions,
+) -> CompareReport {
+    let mut std_spill: Vec<u8> = Vec::new();
+    let mut user_spill: Vec<u8> = Vec::new();
+    let mut token_index: u64 = 0;
+
+    let failure = loop {
+        let std_token = poll_token(std_reader, &mut std_spill, None, usize::MAX);
+        let user_token = poll_token(
+            user_reader,
+            &mut user_spill,
+            None,
+            std_token.len.saturating_add(MAX_EXTRA_LEN),
+        );
+        token_index += 1;
+
+        let ended = std_token.literal.is_empty() && user_token.literal.is_empty();
+        let verdict = if user_token.is_overlong() && !std_token.literal.is_empty() {
+            Some(MismatchReason::TokenMismatch)
+        } else {
+            compare_tokens(options, std_token.literal, user_token.literal)
+        };
+        let failure = verdict.map(|reason| {
+            let fraction_mismatch = FractionMismatch {
+                token_index,
+                std_literal: std_token.literal(),
+                user_literal: user_token.literal(),
+            };
+            (reason, fraction_mismatch)
+        });
+
+        let (std_len, user_len) = (std_token.len, user_token.len);
+        let (std_pending, user_pending) = (std_token.pending, user_token.pending);
+        std_reader.consume(std_pending);
+        user_reader.consume(user_pending);
+
+        let failure = failure.map(|(reason, fraction_mismatch)| {
+            let mismatch = Mismatch {
+                std: token_position(std_reader, std_len),
+                user: token_position(user_reader, user_len),
+                reason,
+            };
+            (mismatch, fraction_mismatch)
+        });
+
+        if ended || failure.is_some() {
+            break failure;
+        }
+    };
+
+    let std_consumed = std_reader.position().offset;
+    let user_consumed = user_reader.position().offset;
+    match failure {
+        None => CompareReport::new(Comparison::AC, None, std_consumed, user_consumed),
+        Some((mismatch, fraction_mismatch)) => {
+            let mut report =
+                CompareReport::new(Comparison::WA, Some(mismatch), std_consumed, user_consumed);
+            report.fraction_mismatch = Some(fraction_mismatch);
+            report
+        }
+    }
+}
+
+#[test]
+fn test_fraction_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let ret = fraction_compare(&mut std, &mut user, &FractionOptions::new());
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"", b"");
+    judge!(AC, b"1/2 3", b"2/4\n3/1");
+    judge!(AC, b"-1/3", b"-2/6");
+    judge!(AC, b"0 -0/5", b"0/7 +0");
+    judge!(AC, b"007/010", b"7/10");
+    judge!(
+        AC,
+        b"1/123456789012345678901234567890",
+        b"2/246913578024691357802469135780"
+    );
+    judge!(
+        WA,
+        b"1/123456789012345678901234567890",
+        b"1/123456789012345678901234567891"
+    );
+    judge!(WA, b"1/2", b"-1/2");
+    judge!(WA, b"1/2", b"1/2 3");
+    judge!(WA, b"1/2 3", b"1/2");
+    judge!(WA, b"1/2", b"1/0");
+    judge!(WA, b"1/2", b"1/-2");
+    judge!(WA, b"1/2", b"1//2");
+    judge!(WA, b"1/2", b"/2");
+    judge!(WA, b"1/2", b"0.5");
+    judge!(WA, b"1", b"1_0/10");
+
+    // scaled by 10^64 at most
+    let zeros = "0".repeat(64);
+    let scaled = format!("1{}/2{}", zeros, zeros);
+    let padded = format!("{}1/{}2", zeros, zeros);
+    let too_long = format!("10{}/20{}", zeros, zeros);
+    let huge = format!("1/{}", "9".repeat(1 << 16));
+    judge!(AC, b"1/2", scaled);
+    judge!(AC, b"1/2", padded);
+    judge!(WA, b"1/2", too_long);
+    judge!(WA, b"1/2", huge);
+
+    // the whole token is limited too
+    let overlong = format!("{}1/2", "0".repeat(2000));
+    judge!(WA, b"1/2", overlong);
+    let overlong_then_more = format!("{} 1/2", overlong);
+    judge!(WA, b"1/2", overlong_then_more);
+}
+
+#[test]
+fn test_fraction_reduced() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            let mut options = FractionOptions::new();
+            options.reduced = true;
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = fraction_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"2/4", b"1/2");
+    judge!(AC, b"3", b"3/1");
+    judge!(AC, b"0", b"0/1");
+    judge!(AC, b"-1/3", b"-1/3");
+    judge!(WA, b"1/2", b"2/4");
+    judge!(WA, b"-1/3", b"-2/6");
+    judge!(WA, b"0", b"0/5");
+}
+
+#[test]
+fn test_fraction_report() {
+    use crate::byte_read::ByteReader;
+
+    let report_of = |std: &[u8], user: &[u8], reduced: bool| {
+        let mut options = FractionOptions::new();
+        options.reduced = reduced;
+        let mut std = ByteReader::with_capacity(64, std);
+        let mut user = ByteReader::with_capacity(64, user);
+        fraction_compare(&mut std, &mut user, &options)
+    };
+
+    let ret = report_of(b"1/2 1/3\n1/4", b"1/2 1/3 1/5", false);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 1));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (1, 9));
+    let m = ret.fraction_mismatch.unwrap();
+    assert_eq!(m.token_index, 3);
+    assert_eq!(m.std_literal.as_deref(), Some("1/4"));
+    assert_eq!(m.user_literal.as_deref(), Some("1/5"));
+
+    let reason_of = |std: &[u8], user: &[u8], reduced: bool| {
+        report_of(std, user, reduced).mismatch.unwrap().reason
+    };
+    assert_eq!(
+        reason_of(b"1/2", b"2/4", true),
+        MismatchReason::FormatMismatch
+    );
+    assert_eq!(
+        reason_of(b"1/2", b"1/x", true),
+        MismatchReason::InvalidToken
+    );
+    assert_eq!(
+        reason_of(b"1/2", b"1/2 3", false),
+        MismatchReason::ExtraOutput
+    );
+    assert_eq!(
+        reason_of(b"1/2 3", b"1/2", false),
+        MismatchReason::MissingOutput
+    );
+
+    let ret = report_of(b"1/2", b"", false);
+    let m = ret.fraction_mismatch.unwrap();
+    assert_eq!(m.user_literal, None);
+}
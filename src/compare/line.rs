@@ -0,0 +1,222 @@
+use super::normal::{handle_eof, poll_diff, poll_endline};
+use super::{byte_position, catch_io, CompareError, CompareReport, Comparison};
+use super::{ByteEq, Exact, IgnoreCase, Mismatch, MismatchReason, TextOptions};
+
+use crate::byte_read::{IoByte, TrackedRead};
+
+use std::panic::AssertUnwindSafe;
+
+pub fn try_line_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        line_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)
+}
+
+fn line_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> CompareReport {
+    let mismatch = if options.ignore_case {
+        line_verdict::<IgnoreCase>(std_reader, user_reader)
+    } else {
+        line_verdict::<Exact>(std_reader, user_reader)
+    };
+    let comparison = match mismatch {
+        None => Comparison::AC,
+        Some(_) => Comparison::WA,
+    };
+    CompareReport::new(
+        comparison,
+        mismatch,
+        std_reader.position().offset,
+        user_reader.position().offset,
+    )
+}
+
+#[inline(never)]
+fn line_verdict<E: ByteEq>(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+) -> Option<Mismatch> {
+    let mut std_byte = std_reader.next_byte();
+    let mut user_byte = user_reader.next_byte();
+
+    macro_rules! mismatch {
+        ($reason:ident) => {
+            Mismatch {
+                std: byte_position(std_reader, std_byte),
+                user: byte_position(user_reader, user_byte),
+                reason: MismatchReason::$reason,
+            }
+        };
+    }
+
+    loop {
+        if std_byte.is_eof() {
+            match handle_eof(user_reader, user_byte, Comparison::AC) {
+                Comparison::AC => return None,
+                _ => return Some(mismatch!(ExtraOutput)),
+            }
+        }
+
+        if user_byte.is_eof() {
+            match handle_eof(std_reader, std_byte, Comparison::AC) {
+                Comparison::AC => return None,
+                _ => return Some(mismatch!(MissingOutput)),
+            }
+        }
+
+        let (a, b) = (std_byte.as_u8(), user_byte.as_u8());
+        if E::eq(a, b) {
+            let ret = poll_diff::<E>(std_reader, user_reader, &mut 0);
+            std_byte = ret.0;
+            user_byte = ret.1;
+            continue;
+        }
+
+        let flaga = a.is_ascii_whitespace();
+        let flagb = b.is_ascii_whitespace();
+
+        // a != b
+        // at least one of them is non-space
+        if !flaga | !flagb {
+            if a == b'\n' || b == b'\n' {
+                return Some(mismatch!(LineBreakMismatch));
+            }
+            return Some(mismatch!(TokenMismatch));
+        }
+
+        // a != b
+        // both of them are spaces, which is fine only if both lines end here
+        let mismatch = mismatch!(TokenMismatch);
+        let enda = poll_trailing(std_reader, std_byte);
+        let endb = poll_trailing(user_reader, user_byte);
+
+        match (enda, endb) {
+            (true, true) => {
+                std_byte = std_reader.next_byte();
+                user_byte = user_reader.next_byte();
+            }
+            (false, false) => return Some(mismatch),
+            _ => {
+                return Some(Mismatch {
+                    reason: MismatchReason::LineBreakMismatch,
+                    ..mismatch
+                })
+            }
+        }
+    }
+}
+
+/// poll until b'\n' from a space `byte`.
+/// ensure that the rest of the line are ascii whitespaces
+#[inline]
+fn poll_trailing(reader: &mut impl TrackedRead, byte: IoByte) -> bool {
+    byte.as_u8() == b'\n' || poll_endline(reader)
+}
+
+#[test]
+fn test_line_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let ret = line_compare(&mut std, &mut user, &TextOptions::new());
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"", b"");
+    judge!(AC, b"\n", b"");
+    judge!(AC, b"", b"\r\n \n");
+    judge!(AC, b"1", b"1\n");
+    judge!(AC, b"1 \n", b"1");
+    judge!(AC, b"1 2\n3 4", b"1 2\r\n3 4\r\n");
+    judge!(AC, b"1 2  \n3 4", b"1 2\t\n3 4");
+    judge!(AC, b"1 2\n3 4", b"1 2 \r\n3 4");
+    judge!(AC, b"  +--+\n  |  |\n", b"  +--+  \r\n  |  |\r\n\r\n");
+    judge!(AC, b"1\r\n\r\n\r\n", b"1  ");
+    judge!(WA, b"1", b"2");
+    judge!(WA, b"1 2", b"12");
+    judge!(WA, b"12", b"1 2");
+    judge!(WA, b"1 2", b"1  2");
+    judge!(WA, b"1 2", b"1\t2");
+    judge!(WA, b" 1", b"1");
+    judge!(WA, b"1", b" 1");
+    judge!(WA, b"1\n2", b"1 2");
+    judge!(WA, b"1 \n2", b"1 2");
+    judge!(WA, b"1\n\n2", b"1\n2");
+    judge!(WA, b"1\n2", b"1\n\n2");
+    judge!(WA, b"1\n", b"1\na");
+    judge!(WA, b"1\na", b"1\n");
+    judge!(WA, b"1 a", b"1 ");
+    judge!(WA, b"1  a", b"1\t\tb");
+}
+
+#[test]
+fn test_line_long() {
+    use crate::byte_read::ByteReader;
+
+    // a difference right at the end of the first 1 KiB block
+    let std = vec![b'a'; 2000];
+    for offset in 1016..1048 {
+        let mut user = std.clone();
+        user[offset] = b'b';
+        for &ignore_case in [false, true].iter() {
+            let mut options = TextOptions::new();
+            options.ignore_case = ignore_case;
+            for &capacity in [3, 64, 8192].iter() {
+                let mut std = ByteReader::with_capacity(capacity, &std[..]);
+                let mut user = ByteReader::with_capacity(capacity, &user[..]);
+                let ret = line_compare(&mut std, &mut user, &options);
+                assert_eq!(ret.comparison, Comparison::WA);
+                assert_eq!(ret.mismatch.unwrap().std.offset, offset as u64);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_line_report() {
+    use crate::byte_read::ByteReader;
+
+    let reason_of = |std: &[u8], user: &[u8]| {
+        let mut std = ByteReader::with_capacity(64, std);
+        let mut user = ByteReader::with_capacity(64, user);
+        line_compare(&mut std, &mut user, &TextOptions::new())
+            .mismatch
+            .unwrap()
+    };
+
+    let mismatch = reason_of(b"ab\n1 2\n", b"ab\r\n1  2\n");
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 3));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (2, 3));
+
+    assert_eq!(
+        reason_of(b"1\n2", b"1 2").reason,
+        MismatchReason::LineBreakMismatch
+    );
+    assert_eq!(
+        reason_of(b"1 \n2", b"1 2").reason,
+        MismatchReason::LineBreakMismatch
+    );
+    assert_eq!(reason_of(b"1", b"1\n2").reason, MismatchReason::ExtraOutput);
+    assert_eq!(
+        reason_of(b"1\n2", b"1").reason,
+        MismatchReason::MissingOutput
+    );
+}
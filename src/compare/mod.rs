@@ -1,17 +1,49 @@
 mod normal;
 
+#[deny(unsafe_code)]
+mod token;
+
+#[deny(unsafe_code)]
+mod line;
+
 #[deny(unsafe_code)]
 mod strict;
 
 #[deny(unsafe_code)]
 mod float;
 
+#[deny(unsafe_code)]
+mod unordered;
+
+#[deny(unsafe_code)]
+mod number;
+
+#[deny(unsafe_code)]
+mod fraction;
+
+#[deny(unsafe_code)]
+mod embedded;
+
+use crate::byte_read::{IoByte, Position, TrackedRead};
+
 use std::panic::{catch_unwind, resume_unwind, UnwindSafe};
 use std::{fmt, io, panic};
 
-pub use self::float::try_float_compare;
+pub use self::embedded::{try_embedded_compare, EmbeddedOptions};
+pub use self::float::{
+    try_float_compare, Decimals, FloatError, FloatMismatch, FloatOptions, FloatStats,
+};
+pub use self::float::{Columns, FloatNorm, LiteralPolicy, Norm, SigFigs};
+pub use self::fraction::{try_fraction_compare, FractionMismatch, FractionOptions};
+pub use self::line::try_line_compare;
 pub use self::normal::try_normal_compare;
+pub use self::number::{try_number_compare, Decimal, NumberMismatch, NumberOptions};
 pub use self::strict::try_strict_compare;
+pub use self::token::try_token_compare;
+pub use self::unordered::{try_float_set_compare, FloatSetOptions};
+pub use self::unordered::{
+    try_unordered_compare, UnorderedBy, UnorderedMismatch, UnorderedOptions,
+};
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,9 +53,149 @@ pub enum Comparison {
     PE = 2,
 }
 
+/// Why std and user diverge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MismatchReason {
+    /// User has more content after std ends.
+    ExtraOutput,
+    /// User ends before std.
+    MissingOutput,
+    /// Contents differ.
+    TokenMismatch,
+    /// One side ends a line while the other continues it.
+    LineBreakMismatch,
+    /// Only whitespace differs (PE).
+    WhitespaceOnly,
+    /// A token is not a valid number.
+    InvalidToken,
+    /// A number is close enough but printed in a wrong format.
+    FormatMismatch,
+}
+
+/// Where std and user first diverge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    pub std: Position,
+    pub user: Position,
+    pub reason: MismatchReason,
+}
+
+/// The verdict of a comparison together with what led to it.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareReport {
+    pub comparison: Comparison,
+    /// The first divergence. `None` if the comparison is AC.
+    pub mismatch: Option<Mismatch>,
+    /// Bytes consumed from std
+    pub std_consumed: u64,
+    /// Bytes consumed from user
+    pub user_consumed: u64,
+    /// Details of the failing token in float mode, or the failing number in embedded mode
+    pub float_mismatch: Option<FloatMismatch>,
+    /// Error statistics in float mode with [`FloatOptions::full_scan`]
+    pub float_stats: Option<FloatStats>,
+    /// Error norms in float mode with [`FloatOptions::norm`]
+    pub float_norm: Option<FloatNorm>,
+    /// The first unmatched elements in unordered mode
+    pub unordered_mismatch: Option<UnorderedMismatch>,
+    /// Details of the failing token in number mode
+    pub number_mismatch: Option<NumberMismatch>,
+    /// Details of the failing token in fraction mode
+    pub fraction_mismatch: Option<FractionMismatch>,
+}
+
+impl CompareReport {
+    pub(crate) fn new(
+        comparison: Comparison,
+        mismatch: Option<Mismatch>,
+        std_consumed: u64,
+        user_consumed: u64,
+    ) -> Self {
+        Self {
+            comparison,
+            mismatch,
+            std_consumed,
+            user_consumed,
+            float_mismatch: None,
+            float_stats: None,
+            float_norm: None,
+            unordered_mismatch: None,
+            number_mismatch: None,
+            fraction_mismatch: None,
+        }
+    }
+}
+
+/// Options of text modes (normal, line, token and strict).
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextOptions {
+    /// Compares ascii letters case-insensitively.
+    pub ignore_case: bool,
+}
+
+impl TextOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// How two bytes are considered equal.
+trait ByteEq {
+    fn eq(a: u8, b: u8) -> bool;
+    fn eq_io(a: IoByte, b: IoByte) -> bool;
+    fn eq_slice(a: &[u8], b: &[u8]) -> bool;
+}
+
+struct Exact;
+
+impl ByteEq for Exact {
+    #[inline(always)]
+    fn eq(a: u8, b: u8) -> bool {
+        a == b
+    }
+    #[inline(always)]
+    fn eq_io(a: IoByte, b: IoByte) -> bool {
+        a == b
+    }
+    #[inline(always)]
+    fn eq_slice(a: &[u8], b: &[u8]) -> bool {
+        a == b
+    }
+}
+
+struct IgnoreCase;
+
+impl ByteEq for IgnoreCase {
+    #[inline(always)]
+    fn eq(a: u8, b: u8) -> bool {
+        a.eq_ignore_ascii_case(&b)
+    }
+    #[inline(always)]
+    fn eq_io(a: IoByte, b: IoByte) -> bool {
+        a.is_eof() == b.is_eof() && a.as_u8().eq_ignore_ascii_case(&b.as_u8())
+    }
+    #[inline(always)]
+    fn eq_slice(a: &[u8], b: &[u8]) -> bool {
+        a.eq_ignore_ascii_case(b)
+    }
+}
+
+/// Returns the position of `byte`, which is the last byte read from `reader`.
+fn byte_position(reader: &impl TrackedRead, byte: IoByte) -> Position {
+    if byte.is_eof() {
+        reader.position()
+    } else {
+        reader.last_position()
+    }
+}
+
 #[derive(Debug)]
 pub enum CompareError {
     Io(io::Error),
+    /// The elements to hold exceed the memory limit.
+    TooLarge,
 }
 
 impl fmt::Display for CompareError {
@@ -32,6 +204,9 @@ impl fmt::Display for CompareError {
             CompareError::Io(e) => {
                 write!(f, "CompareError::Io: {}", e)
             }
+            CompareError::TooLarge => {
+                write!(f, "CompareError::TooLarge: memory limit exceeded")
+            }
         }
     }
 }
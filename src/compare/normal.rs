@@ -1,39 +1,90 @@
-use super::{catch_io, CompareError, Comparison};
+use super::{byte_position, catch_io, CompareError, CompareReport, Comparison};
+use super::{ByteEq, Exact, IgnoreCase, Mismatch, MismatchReason, TextOptions};
 
-use crate::byte_read::{ByteRead, IoByte};
+use crate::byte_read::{IoByte, TrackedRead};
 
 use std::cmp::{Ord, Ordering};
 use std::panic::{panic_any, AssertUnwindSafe};
 
 pub fn try_normal_compare(
-    std_reader: &mut impl ByteRead,
-    user_reader: &mut impl ByteRead,
-) -> Result<Comparison, CompareError> {
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> Result<CompareReport, CompareError> {
     catch_io(AssertUnwindSafe(move || {
-        normal_compare(std_reader, user_reader)
+        normal_compare(std_reader, user_reader, options)
     }))
     .map_err(CompareError::Io)
 }
 
+fn normal_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> CompareReport {
+    let (comparison, mismatch) = if options.ignore_case {
+        normal_verdict::<IgnoreCase>(std_reader, user_reader)
+    } else {
+        normal_verdict::<Exact>(std_reader, user_reader)
+    };
+    CompareReport::new(
+        comparison,
+        mismatch,
+        std_reader.position().offset,
+        user_reader.position().offset,
+    )
+}
+
 #[inline(never)]
-fn normal_compare(std_reader: &mut impl ByteRead, user_reader: &mut impl ByteRead) -> Comparison {
+fn normal_verdict<E: ByteEq>(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+) -> (Comparison, Option<Mismatch>) {
     let mut std_byte = std_reader.next_byte();
     let mut user_byte = user_reader.next_byte();
 
     let mut ans = Comparison::AC;
+    let mut pe_mismatch: Option<Mismatch> = None;
+
+    macro_rules! mismatch {
+        ($reason:ident) => {
+            Some(Mismatch {
+                std: byte_position(std_reader, std_byte),
+                user: byte_position(user_reader, user_byte),
+                reason: MismatchReason::$reason,
+            })
+        };
+    }
+
+    macro_rules! wa {
+        ($reason:ident) => {
+            return (Comparison::WA, mismatch!($reason))
+        };
+    }
+
+    macro_rules! done {
+        ($ans:expr, $reason:ident) => {{
+            let ans = $ans;
+            return match ans {
+                Comparison::AC => (ans, None),
+                Comparison::PE => (ans, pe_mismatch),
+                Comparison::WA => (ans, mismatch!($reason)),
+            };
+        }};
+    }
 
     loop {
         if std_byte.is_eof() {
-            return handle_eof(user_reader, user_byte, ans);
+            done!(handle_eof(user_reader, user_byte, ans), ExtraOutput);
         }
 
         if user_byte.is_eof() {
-            return handle_eof(std_reader, std_byte, ans);
+            done!(handle_eof(std_reader, std_byte, ans), MissingOutput);
         }
 
         let (a, b) = (std_byte.as_u8(), user_byte.as_u8());
-        if a == b {
-            let ret = poll_diff(std_reader, user_reader);
+        if E::eq(a, b) {
+            let ret = poll_diff::<E>(std_reader, user_reader, &mut 0);
             std_byte = ret.0;
             user_byte = ret.1;
             continue;
@@ -41,26 +92,26 @@ fn normal_compare(std_reader: &mut impl ByteRead, user_reader: &mut impl ByteRea
 
         if a == b'\n' {
             if !b.is_ascii_whitespace() {
-                return Comparison::WA;
+                wa!(LineBreakMismatch);
             }
             if poll_endline(user_reader) {
                 std_byte = std_reader.next_byte();
                 user_byte = user_reader.next_byte();
                 continue;
             } else {
-                return Comparison::WA;
+                wa!(LineBreakMismatch);
             }
         }
         if b == b'\n' {
             if !a.is_ascii_whitespace() {
-                return Comparison::WA;
+                wa!(LineBreakMismatch);
             }
             if poll_endline(std_reader) {
                 std_byte = std_reader.next_byte();
                 user_byte = user_reader.next_byte();
                 continue;
             } else {
-                return Comparison::WA;
+                wa!(LineBreakMismatch);
             }
         }
 
@@ -70,9 +121,16 @@ fn normal_compare(std_reader: &mut impl ByteRead, user_reader: &mut impl ByteRea
         // a != b
         // both of them are non-space
         if !flaga & !flagb {
-            return Comparison::WA;
+            wa!(TokenMismatch);
         }
 
+        // the first whitespace difference is where a PE starts
+        let space_mismatch = if pe_mismatch.is_none() {
+            mismatch!(WhitespaceOnly)
+        } else {
+            None
+        };
+
         // a != b
         // both of them are not non-space
         if flaga {
@@ -96,21 +154,26 @@ fn normal_compare(std_reader: &mut impl ByteRead, user_reader: &mut impl ByteRea
             continue;
         }
         if flaga | flagb {
-            return Comparison::WA;
+            wa!(LineBreakMismatch);
         }
-        if a == b {
+        if E::eq(a, b) {
             ans = Comparison::PE;
+            pe_mismatch = pe_mismatch.or(space_mismatch);
             std_byte = std_reader.next_byte();
             user_byte = user_reader.next_byte();
             continue;
         } else {
-            return Comparison::WA;
+            wa!(TokenMismatch);
         }
     }
 }
 
 #[inline(never)]
-fn handle_eof(rhs: &mut impl ByteRead, rhs_byte: IoByte, ans: Comparison) -> Comparison {
+pub(super) fn handle_eof(
+    rhs: &mut impl TrackedRead,
+    rhs_byte: IoByte,
+    ans: Comparison,
+) -> Comparison {
     if rhs_byte.is_eof() {
         return ans;
     }
@@ -124,8 +187,14 @@ fn handle_eof(rhs: &mut impl ByteRead, rhs_byte: IoByte, ans: Comparison) -> Com
     }
 }
 
+/// poll until the first different bytes.
+/// `last` is set to the last equal byte, if any.
 #[inline]
-fn poll_diff(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> (IoByte, IoByte) {
+pub(super) fn poll_diff<E: ByteEq>(
+    lhs: &mut impl TrackedRead,
+    rhs: &mut impl TrackedRead,
+    last: &mut u8,
+) -> (IoByte, IoByte) {
     {
         let lhs_buf = match lhs.fill_buf() {
             Ok(b) => b,
@@ -139,7 +208,8 @@ fn poll_diff(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> (IoByte, IoByt
             unsafe {
                 let lhs_buf = lhs_buf.get_unchecked(..8);
                 let rhs_buf = rhs_buf.get_unchecked(..8);
-                if lhs_buf == rhs_buf {
+                if E::eq_slice(lhs_buf, rhs_buf) {
+                    *last = *lhs_buf.get_unchecked(7);
                     lhs.consume_unchecked(8);
                     rhs.consume_unchecked(8);
                 }
@@ -157,23 +227,24 @@ fn poll_diff(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> (IoByte, IoByt
         rhs_byte = rhs.next_byte();
         cmp_cnt += 1;
 
-        if cmp_cnt >= 1024 {
-            break;
-        }
-
-        if lhs_byte == rhs_byte {
+        if E::eq_io(lhs_byte, rhs_byte) {
             eq_cnt += 1;
             if lhs_byte.is_eof() {
                 return (lhs_byte, rhs_byte);
             }
+            *last = lhs_byte.as_u8();
         } else {
             return (lhs_byte, rhs_byte);
         }
+
+        if cmp_cnt >= 1024 {
+            break;
+        }
     }
 
     loop {
         if cmp_cnt >= 1024 && eq_cnt > cmp_cnt * 255 / 256 {
-            let len = diff_block(lhs, rhs);
+            let len = diff_block::<E>(lhs, rhs, last);
             if len == 0 {
                 eq_cnt = 0;
                 cmp_cnt = 0;
@@ -187,11 +258,12 @@ fn poll_diff(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> (IoByte, IoByt
         rhs_byte = rhs.next_byte();
         cmp_cnt += 1;
 
-        if lhs_byte == rhs_byte {
+        if E::eq_io(lhs_byte, rhs_byte) {
             eq_cnt += 1;
             if lhs_byte.is_eof() {
                 return (lhs_byte, rhs_byte);
             }
+            *last = lhs_byte.as_u8();
         } else {
             return (lhs_byte, rhs_byte);
         }
@@ -199,7 +271,11 @@ fn poll_diff(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> (IoByte, IoByt
 }
 
 #[inline]
-fn diff_block(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> usize {
+fn diff_block<E: ByteEq>(
+    lhs: &mut impl TrackedRead,
+    rhs: &mut impl TrackedRead,
+    last: &mut u8,
+) -> usize {
     let mut total: usize = 0;
     loop {
         let lhs_buf: &[u8] = match lhs.fill_buf() {
@@ -222,7 +298,8 @@ fn diff_block(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> usize {
             break total;
         }
 
-        if lhs_buf == rhs_buf {
+        if E::eq_slice(lhs_buf, rhs_buf) {
+            *last = lhs_buf[len - 1];
             lhs.consume(len);
             rhs.consume(len);
             total += len;
@@ -236,7 +313,7 @@ fn diff_block(lhs: &mut impl ByteRead, rhs: &mut impl ByteRead) -> usize {
 /// poll until eof.
 /// ensure that all chars remaining in `chars` are ascii whitespaces
 #[inline]
-fn poll_eof(reader: &mut impl ByteRead) -> bool {
+fn poll_eof(reader: &mut impl TrackedRead) -> bool {
     loop {
         let b = reader.next_byte();
         if b.is_eof() {
@@ -251,7 +328,7 @@ fn poll_eof(reader: &mut impl ByteRead) -> bool {
 /// poll until b'\n'.
 /// ensure that all chars remaining in `chars` line are ascii whitespaces
 #[inline(always)]
-fn poll_endline(reader: &mut impl ByteRead) -> bool {
+pub(super) fn poll_endline(reader: &mut impl TrackedRead) -> bool {
     let mut b = reader.next_byte();
     loop {
         if b.is_eof() || b.as_u8() == b'\n' {
@@ -266,7 +343,7 @@ fn poll_endline(reader: &mut impl ByteRead) -> bool {
 
 /// poll until b'\n' or non-space or EOF
 #[inline(always)]
-fn poll_nonspace(reader: &mut impl ByteRead) -> IoByte {
+fn poll_nonspace(reader: &mut impl TrackedRead) -> IoByte {
     loop {
         let b: IoByte = reader.next_byte();
         if b.is_eof() || b.as_u8() == b'\n' || !b.as_u8().is_ascii_whitespace() {
@@ -275,15 +352,33 @@ fn poll_nonspace(reader: &mut impl ByteRead) -> IoByte {
     }
 }
 
+#[cfg(test)]
+fn judge_normal(std: &[u8], user: &[u8]) -> CompareReport {
+    judge_normal_with(std, user, &TextOptions::new())
+}
+
+#[cfg(test)]
+fn judge_normal_with(std: &[u8], user: &[u8], options: &TextOptions) -> CompareReport {
+    use crate::byte_read::ByteReader;
+
+    let mut reports = [1, 3, 64].iter().map(|&capacity| {
+        let mut std_reader = ByteReader::with_capacity(capacity, std);
+        let mut user_reader = ByteReader::with_capacity(capacity, user);
+        normal_compare(&mut std_reader, &mut user_reader, options)
+    });
+    let ret = reports.next().unwrap();
+    for other in reports {
+        assert_eq!(ret, other);
+    }
+    ret
+}
+
 #[test]
 fn test_normal_comparer() {
     macro_rules! judge {
         ($ret:expr, $std:expr,$user:expr) => {{
-            let mut std: &[u8] = $std.as_ref();
-            let mut user: &[u8] = $user.as_ref();
-
-            let ret = normal_compare(&mut std, &mut user);
-            assert_eq!(ret, $ret);
+            let ret = judge_normal($std.as_ref(), $user.as_ref());
+            assert_eq!(ret.comparison, $ret);
         }};
     }
 
@@ -338,3 +433,103 @@ fn test_normal_comparer() {
     judge!(AC, b"1\n\n3\n", b"1\r\n  \r\n3\t\n");
     judge!(WA, b"1\n3\n", b"1\r\n  \r\n3\t\n");
 }
+
+#[test]
+fn test_normal_report() {
+    use crate::byte_read::Position;
+
+    macro_rules! judge {
+        ($ret:expr, $reason:ident, $std:expr, $user:expr, $std_pos:expr, $user_pos:expr) => {{
+            let ret = judge_normal($std.as_ref(), $user.as_ref());
+            assert_eq!(ret.comparison, $ret);
+            let (offset, line, column) = $std_pos;
+            let std = Position {
+                offset,
+                line,
+                column,
+            };
+            let (offset, line, column) = $user_pos;
+            let user = Position {
+                offset,
+                line,
+                column,
+            };
+            let reason = MismatchReason::$reason;
+            assert_eq!(ret.mismatch, Some(Mismatch { std, user, reason }));
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(WA, TokenMismatch, b"1", b"2", (0, 1, 1), (0, 1, 1));
+    judge!(
+        WA,
+        TokenMismatch,
+        b"1 2\n3 4\n",
+        b"1 2\n3 5\n",
+        (6, 2, 3),
+        (6, 2, 3)
+    );
+    judge!(WA, LineBreakMismatch, b"1\n2", b"12", (1, 1, 2), (1, 1, 2));
+    judge!(WA, ExtraOutput, b"1\n", b"1\r\n\r\nx", (2, 2, 1), (5, 3, 1));
+    judge!(WA, MissingOutput, b"1\nab", b"1\r\na", (3, 2, 2), (4, 2, 2));
+    judge!(
+        PE,
+        WhitespaceOnly,
+        b"a b\nc d\n",
+        b"a b\nc  d\n",
+        (6, 2, 3),
+        (6, 2, 3)
+    );
+    judge!(
+        PE,
+        WhitespaceOnly,
+        b"x\n1 2",
+        b"x\r\n1\t2",
+        (3, 2, 2),
+        (4, 2, 2)
+    );
+
+    let reason_of = |std: &[u8], user: &[u8]| judge_normal(std, user).mismatch.unwrap().reason;
+    assert_eq!(reason_of(b"1 2", b"1 2 3"), MismatchReason::ExtraOutput);
+    assert_eq!(reason_of(b"1\n2\n", b"1\n"), MismatchReason::MissingOutput);
+    assert_eq!(
+        reason_of(b"1 2\n3", b"1\n2 3"),
+        MismatchReason::LineBreakMismatch
+    );
+    assert_eq!(reason_of(b"1 a", b"1 b"), MismatchReason::TokenMismatch);
+
+    let ret = judge_normal(b"1 2\n", b"1 2\r\n\n");
+    assert_eq!(ret.comparison, AC);
+    assert_eq!(ret.mismatch, None);
+    assert_eq!((ret.std_consumed, ret.user_consumed), (4, 6));
+}
+
+#[test]
+fn test_normal_ignore_case() {
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            let mut options = TextOptions::new();
+            options.ignore_case = true;
+            let ret = judge_normal_with($std.as_ref(), $user.as_ref(), &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"YES", b"yes");
+    judge!(AC, b"Yes\nNo\n", b"yES\r\nnO");
+    judge!(AC, b"deadBEEF", b"DEADbeef");
+    judge!(
+        AC,
+        b"0123456789abcdef0123456789",
+        b"0123456789ABCDEF0123456789"
+    );
+    judge!(PE, b"YES NO", b"yes  no");
+    judge!(WA, b"YES", b"YEP");
+    judge!(WA, b"[", b"{");
+    judge!(WA, b"@", b"`");
+
+    assert_eq!(judge_normal(b"YES", b"yes").comparison, WA);
+}
@@ -0,0 +1,445 @@
+use super::float::{poll_token, token_position, MAX_EXTRA_LEN};
+use super::{catch_io, CompareError, CompareReport, Comparison, Mismatch, MismatchReason};
+
+use crate::byte_read::TrackedRead;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+
+pub fn try_number_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &NumberOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        number_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)
+}
+
+/// Options of number mode.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NumberOptions {
+    /// Max absolute difference. `None` requires equal values.
+    pub tolerance: Option<Decimal>,
+}
+
+impl NumberOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// The first number token pair that differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumberMismatch {
+    /// 1-based index of the token pair
+    pub token_index: u64,
+    /// `None` if std has ended.
+    pub std_literal: Option<String>,
+    /// `None` if user has ended.
+    pub user_literal: Option<String>,
+    /// `|user - std|`, if both tokens are numbers.
+    pub abs_error: Option<Decimal>,
+}
+
+/// Exponents beyond this are rejected, which bounds the digits of a difference.
+const MAX_EXPONENT: i64 = 1_000_000;
+
+/// An exact decimal number.
+///
+/// Accepts `[+-]?[0-9]*(\.[0-9]*)?([eE][+-]?[0-9]+)?` with at least one mantissa digit.
+/// The exponent must be within ±1000000.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal {
+    neg: bool,
+    /// Digits without leading or trailing zeros, most significant first. Empty for zero.
+    coef: Vec<u8>,
+    /// The value is `coef * 10^exp`.
+    exp: i64,
+}
+
+impl Decimal {
+    pub fn parse(literal: &[u8]) -> Option<Self> {
+        let (neg, rest) = match literal.first() {
+            Some(b'-') => (true, &literal[1..]),
+            Some(b'+') => (false, &literal[1..]),
+            _ => (false, literal),
+        };
+        let (mantissa, exponent) = match rest.iter().position(|&b| b == b'e' || b == b'E') {
+            Some(idx) => (&rest[..idx], Some(&rest[idx + 1..])),
+            None => (rest, None),
+        };
+        let (int, frac) = match mantissa.iter().position(|&b| b == b'.') {
+            Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+            None => (mantissa, &[][..]),
+        };
+
+        let is_digits = |s: &[u8]| s.iter().all(u8::is_ascii_digit);
+        if int.len() + frac.len() == 0 || !is_digits(int) || !is_digits(frac) {
+            return None;
+        }
+
+        let mut exp: i64 = match exponent {
+            None => 0,
+            Some(e) => {
+                let (neg, digits) = match e.first() {
+                    Some(b'-') => (true, &e[1..]),
+                    Some(b'+') => (false, &e[1..]),
+                    _ => (false, e),
+                };
+                if digits.is_empty() || !is_digits(digits) {
+                    return None;
+                }
+                let mut value: i64 = 0;
+                for &d in digits {
+                    value = value * 10 + i64::from(d - b'0');
+                    if value > MAX_EXPONENT {
+                        return None;
+                    }
+                }
+                if neg {
+                    -value
+                } else {
+                    value
+                }
+            }
+        };
+        exp -= frac.len() as i64;
+
+        let coef: Vec<u8> = int.iter().chain(frac).map(|&d| d - b'0').collect();
+        Some(Self::normalize(neg, coef, exp))
+    }
+
+    fn normalize(neg: bool, mut coef: Vec<u8>, mut exp: i64) -> Self {
+        let start = coef.iter().position(|&d| d != 0).unwrap_or(coef.len());
+        coef.drain(..start);
+        while coef.last() == Some(&0) {
+            coef.pop();
+            exp += 1;
+        }
+        if coef.is_empty() {
+            return Self {
+                neg: false,
+                coef,
+                exp: 0,
+            };
+        }
+        Self { neg, coef, exp }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coef.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.neg
+    }
+
+    /// The value is less than `10^order`, and at least `10^(order-1)` if not zero.
+    fn order(&self) -> i64 {
+        self.coef.len() as i64 + self.exp
+    }
+
+    fn cmp_abs(&self, other: &Self) -> Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self
+                .order()
+                .cmp(&other.order())
+                .then_with(|| self.coef.cmp(&other.coef)),
+        }
+    }
+
+    /// Digits of `self / 10^low`, least significant first.
+    fn digits_from(&self, low: i64, len: usize) -> Vec<u8> {
+        let mut digits = vec![0; len];
+        let shift = (self.exp - low) as usize;
+        for (i, &d) in self.coef.iter().rev().enumerate() {
+            digits[shift + i] = d;
+        }
+        digits
+    }
+
+    /// `|self - other|`
+    pub fn abs_diff(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            let mut ans = if self.is_zero() { other } else { self }.clone();
+            ans.neg = false;
+            return ans;
+        }
+
+        let low = self.exp.min(other.exp);
+        let len = (self.order().max(other.order()) - low) as usize + 1;
+
+        let (big, small) = match self.cmp_abs(other) {
+            Ordering::Less => (other, self),
+            _ => (self, other),
+        };
+        let mut digits = big.digits_from(low, len);
+        let rhs = small.digits_from(low, len);
+
+        if self.neg == other.neg {
+            let mut borrow = 0;
+            for (d, &r) in digits.iter_mut().zip(&rhs) {
+                let mut x = *d as i8 - r as i8 - borrow;
+                borrow = (x < 0) as i8;
+                if x < 0 {
+                    x += 10;
+                }
+                *d = x as u8;
+            }
+        } else {
+            let mut carry = 0;
+            for (d, &r) in digits.iter_mut().zip(&rhs) {
+                let x = *d + r + carry;
+                carry = x / 10;
+                *d = x % 10;
+            }
+        }
+
+        digits.reverse();
+        Self::normalize(false, digits, low)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        if self.neg {
+            f.write_str("-")?;
+        }
+        let digits: String = self.coef.iter().map(|&d| char::from(b'0' + d)).collect();
+        let order = self.order();
+        if self.exp >= 0 && order <= 40 {
+            write!(f, "{}{}", digits, "0".repeat(self.exp as usize))
+        } else if self.exp < 0 && order > 0 {
+            let (int, frac) = digits.split_at(order as usize);
+            write!(f, "{}.{}", int, frac)
+        } else if self.exp < 0 && order > -20 {
+            write!(f, "0.{}{}", "0".repeat((-order) as usize), digits)
+        } else {
+            let (head, tail) = digits.split_at(1);
+            match tail {
+                "" => write!(f, "{}e{}", head, order - 1),
+                _ => write!(f, "{}.{}e{}", head, tail, order - 1),
+            }
+        }
+    }
+}
+
+/// Compares a token pair.
+/// Returns `None` if it passes, or the reason and the error if both are numbers.
+fn compare_tokens(
+    options: &NumberOptions,
+    std: &[u8],
+    user: &[u8],
+) -> Option<(MismatchReason, Option<Decimal>)> {
+    match (std, user) {
+        ([], []) => None,
+        ([], _) => Some((MismatchReason::ExtraOutput, None)),
+        (_, []) => Some((MismatchReason::MissingOutput, None)),
+        _ => match (Decimal::parse(std), Decimal::parse(user)) {
+            (Some(a), Some(b)) => {
+                if a == b {
+                    return None;
+                }
+                let diff = a.abs_diff(&b);
+                if let Some(ref tol) = options.tolerance {
+                    if diff.cmp_abs(tol) != Ordering::Greater {
+                        return None;
+                    }
+                }
+                Some((MismatchReason::TokenMismatch, Some(diff)))
+            }
+            _ => Some((MismatchReason::InvalidToken, None)),
+        },
+    }
+}
+
+fn number_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &NumberOptions,
+) -> CompareReport {
+    let mut std_spill: Vec<u8> = Vec::new();
+    let mut user_spill: Vec<u8> = Vec::new();
+    let mut token_index: u64 = 0;
+
+    let failure = loop {
+        let std_token = poll_token(std_reader, &mut std_spill, None, usize::MAX);
+        let user_token = poll_token(
+            user_reader,
+            &mut user_spill,
+            None,
+            std_token.len.saturating_add(MAX_EXTRA_LEN),
+        );
+        token_index += 1;
+
+        let ended = std_token.literal.is_empty() && user_token.literal.is_empty();
+        let verdict = if user_token.is_overlong() && !std_token.literal.is_empty() {
+            Some((MismatchReason::TokenMismatch, None))
+        } else {
+            compare_tokens(options, std_token.literal, user_token.literal)
+        };
+        let failure = verdict.map(|(reason, abs_error)| {
+            let number_mismatch = NumberMismatch {
+                token_index,
+                std_literal: std_token.literal(),
+                user_literal: user_token.literal(),
+                abs_error,
+            };
+            (reason, number_mismatch)
+        });
+
+        let (std_len, user_len) = (std_token.len, user_token.len);
+        let (std_pending, user_pending) = (std_token.pending, user_token.pending);
+        std_reader.consume(std_pending);
+        user_reader.consume(user_pending);
+
+        if ended || failure.is_some() {
+            break failure.map(|(reason, number_mismatch)| {
+                let mismatch = Mismatch {
+                    std: token_position(std_reader, std_len),
+                    user: token_position(user_reader, user_len),
+                    reason,
+                };
+                (mismatch, number_mismatch)
+            });
+        }
+    };
+
+    let std_consumed = std_reader.position().offset;
+    let user_consumed = user_reader.position().offset;
+    match failure {
+        None => CompareReport::new(Comparison::AC, None, std_consumed, user_consumed),
+        Some((mismatch, number_mismatch)) => {
+            let mut report =
+                CompareReport::new(Comparison::WA, Some(mismatch), std_consumed, user_consumed);
+            report.number_mismatch = Some(number_mismatch);
+            report
+        }
+    }
+}
+
+#[test]
+fn test_number_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let ret = number_compare(&mut std, &mut user, &NumberOptions::new());
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"", b"");
+    judge!(AC, b"1.50 007", b"1.5\n7");
+    judge!(AC, b"0 -0 +0.000", b"-0.0 0e10 0");
+    judge!(AC, b"1e3 1.5E-2", b"1000 0.015");
+    judge!(AC, b"12.", b".12e2");
+    judge!(
+        AC,
+        b"123456789012345678901234567890",
+        b"123456789012345678901234567890.000"
+    );
+    judge!(
+        WA,
+        b"123456789012345678901234567890",
+        b"123456789012345678901234567891"
+    );
+    judge!(WA, b"0.100000000000000000000000000001", b"0.1");
+    judge!(WA, b"1", b"-1");
+    judge!(WA, b"1", b"1 2");
+    judge!(WA, b"1 2", b"1");
+    judge!(WA, b"1", b"x");
+    judge!(WA, b"1", b".");
+    judge!(WA, b"1", b"1e");
+    judge!(WA, b"1", b"1e9999999");
+    judge!(WA, b"1", b"nan");
+}
+
+#[test]
+fn test_number_tolerance() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $tol:expr, $std:expr,$user:expr) => {{
+            let mut options = NumberOptions::new();
+            options.tolerance = Decimal::parse($tol);
+
+            let mut std = ByteReader::with_capacity(64, $std.as_ref());
+            let mut user = ByteReader::with_capacity(64, $user.as_ref());
+            let ret = number_compare(&mut std, &mut user, &options);
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"0.001", b"1.000", b"1.001");
+    judge!(AC, b"0.001", b"1.000", b"0.999");
+    judge!(WA, b"0.001", b"1.000", b"1.0010000000000000000001");
+    judge!(AC, b"0.001", b"-0.0005", b"0.0005");
+    judge!(WA, b"0.001", b"-0.0005", b"0.00051");
+    judge!(
+        AC,
+        b"1",
+        b"99999999999999999999999999999",
+        b"100000000000000000000000000000"
+    );
+    judge!(WA, b"1", b"1e100", b"1.00000000001e100");
+    judge!(AC, b"1e90", b"1e100", b"1.00000000001e100");
+    judge!(WA, b"0.5", b"1e-1000000", b"1e1000000");
+}
+
+#[test]
+fn test_number_report() {
+    use crate::byte_read::ByteReader;
+
+    let mut std = ByteReader::with_capacity(64, &b"1 2.50\n3.25"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1 2.5 3.2"[..]);
+    let ret = number_compare(&mut std, &mut user, &NumberOptions::new());
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 1));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (1, 7));
+    let m = ret.number_mismatch.unwrap();
+    assert_eq!(m.token_index, 3);
+    assert_eq!(m.std_literal.as_deref(), Some("3.25"));
+    assert_eq!(m.user_literal.as_deref(), Some("3.2"));
+    assert_eq!(m.abs_error.unwrap().to_string(), "0.05");
+
+    // an overlong user token is not buffered nor shown in whole
+    let user = format!("1 1.{}", "0".repeat(1 << 16));
+    let mut std = ByteReader::with_capacity(64, &b"1 1"[..]);
+    let mut user = ByteReader::with_capacity(64, user.as_bytes());
+    let ret = number_compare(&mut std, &mut user, &NumberOptions::new());
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!(mismatch.user.offset, 2);
+    let m = ret.number_mismatch.unwrap();
+    assert_eq!(m.user_literal.unwrap().len(), 259);
+    assert_eq!(m.abs_error, None);
+
+    let to_string = |s: &[u8]| Decimal::parse(s).unwrap().to_string();
+    assert_eq!(to_string(b"-00120.500"), "-120.5");
+    assert_eq!(to_string(b"1e3"), "1000");
+    assert_eq!(to_string(b"-0"), "0");
+    assert_eq!(to_string(b"0.00012"), "0.00012");
+    assert_eq!(to_string(b"1.5e100"), "1.5e100");
+    assert_eq!(to_string(b"1e-100"), "1e-100");
+}
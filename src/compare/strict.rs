@@ -1,4 +1,7 @@
-use super::{CompareError, Comparison};
+use super::{ByteEq, CompareError, CompareReport, Comparison, Exact, IgnoreCase};
+use super::{Mismatch, MismatchReason, TextOptions};
+
+use crate::byte_read::Position;
 
 use std::cmp::Ordering;
 use std::io::{self, BufRead};
@@ -6,8 +9,14 @@ use std::io::{self, BufRead};
 pub fn try_strict_compare(
     std_reader: &mut impl BufRead,
     user_reader: &mut impl BufRead,
-) -> Result<Comparison, CompareError> {
-    strict_compare(std_reader, user_reader).map_err(CompareError::Io)
+    options: &TextOptions,
+) -> Result<CompareReport, CompareError> {
+    let ret = if options.ignore_case {
+        strict_compare::<IgnoreCase>(std_reader, user_reader)
+    } else {
+        strict_compare::<Exact>(std_reader, user_reader)
+    };
+    ret.map_err(CompareError::Io)
 }
 
 fn fill_buf(reader: &mut impl BufRead) -> io::Result<Option<&[u8]>> {
@@ -19,47 +28,68 @@ fn fill_buf(reader: &mut impl BufRead) -> io::Result<Option<&[u8]>> {
     }
 }
 
-fn strict_compare(
+fn strict_compare<E: ByteEq>(
     std_reader: &mut impl BufRead,
     user_reader: &mut impl BufRead,
-) -> io::Result<Comparison> {
+) -> io::Result<CompareReport> {
+    // both sides always consume the same bytes
+    let mut pos = Position::START;
+
+    let wa = |pos: Position, reason: MismatchReason| {
+        let mismatch = Mismatch {
+            std: pos,
+            user: pos,
+            reason,
+        };
+        Ok(CompareReport::new(
+            Comparison::WA,
+            Some(mismatch),
+            pos.offset,
+            pos.offset,
+        ))
+    };
+
     loop {
         let std_buf = fill_buf(std_reader)?;
         let user_buf = fill_buf(user_reader)?;
-        let std_len;
-        let user_len;
+        let len;
 
         match (std_buf, user_buf) {
-            (None, None) => return Ok(Comparison::AC),
-            (Some(_), None) | (None, Some(_)) => return Ok(Comparison::WA),
-            (Some(lhs), Some(rhs)) => match lhs.len().cmp(&rhs.len()) {
-                Ordering::Equal => {
-                    if lhs != rhs {
-                        return Ok(Comparison::WA);
-                    }
-                    std_len = lhs.len();
-                    user_len = rhs.len();
-                }
-                Ordering::Less => {
-                    let rhs = &rhs[..lhs.len()];
-                    if lhs != rhs {
-                        return Ok(Comparison::WA);
-                    }
-                    std_len = lhs.len();
-                    user_len = rhs.len();
-                }
-                Ordering::Greater => {
-                    let lhs = &lhs[..rhs.len()];
-                    if lhs != rhs {
-                        return Ok(Comparison::WA);
-                    }
-                    std_len = lhs.len();
-                    user_len = rhs.len();
+            (None, None) => {
+                return Ok(CompareReport::new(
+                    Comparison::AC,
+                    None,
+                    pos.offset,
+                    pos.offset,
+                ))
+            }
+            (Some(_), None) => return wa(pos, MismatchReason::MissingOutput),
+            (None, Some(_)) => return wa(pos, MismatchReason::ExtraOutput),
+            (Some(lhs), Some(rhs)) => {
+                let (lhs, rhs) = match lhs.len().cmp(&rhs.len()) {
+                    Ordering::Equal => (lhs, rhs),
+                    Ordering::Less => (lhs, &rhs[..lhs.len()]),
+                    Ordering::Greater => (&lhs[..rhs.len()], rhs),
+                };
+                if !E::eq_slice(lhs, rhs) {
+                    let idx = lhs
+                        .iter()
+                        .zip(rhs)
+                        .take_while(|&(&a, &b)| E::eq(a, b))
+                        .count();
+                    let reason = if lhs[idx] == b'\n' || rhs[idx] == b'\n' {
+                        MismatchReason::LineBreakMismatch
+                    } else {
+                        MismatchReason::TokenMismatch
+                    };
+                    return wa(pos.advance(&lhs[..idx]), reason);
                 }
-            },
+                pos = pos.advance(lhs);
+                len = lhs.len();
+            }
         }
-        std_reader.consume(std_len);
-        user_reader.consume(user_len);
+        std_reader.consume(len);
+        user_reader.consume(len);
     }
 }
 
@@ -70,8 +100,8 @@ fn test_strict_comparer() {
             let mut std: &[u8] = $std.as_ref();
             let mut user: &[u8] = $user.as_ref();
 
-            let ret = strict_compare(&mut std, &mut user).unwrap();
-            assert_eq!(ret, $ret);
+            let ret = strict_compare::<Exact>(&mut std, &mut user).unwrap();
+            assert_eq!(ret.comparison, $ret);
         }};
     }
 
@@ -87,3 +117,57 @@ fn test_strict_comparer() {
     judge!(WA, b"cc", b"ccc");
     judge!(WA, b"ccc", b"cc");
 }
+
+#[test]
+fn test_strict_report() {
+    let mut std: &[u8] = b"ab\ncd";
+    let mut user: &[u8] = b"ab\nce";
+    let ret = strict_compare::<Exact>(&mut std, &mut user).unwrap();
+    let pos = Position {
+        offset: 4,
+        line: 2,
+        column: 2,
+    };
+    assert_eq!(ret.comparison, Comparison::WA);
+    assert_eq!(ret.mismatch.unwrap().std, pos);
+    assert_eq!(ret.mismatch.unwrap().user, pos);
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::TokenMismatch);
+
+    let reason_of = |mut std: &[u8], mut user: &[u8]| {
+        let ret = strict_compare::<Exact>(&mut std, &mut user).unwrap();
+        ret.mismatch.unwrap().reason
+    };
+    assert_eq!(reason_of(b"ab", b"abc"), MismatchReason::ExtraOutput);
+    assert_eq!(reason_of(b"ab\n", b"ab"), MismatchReason::MissingOutput);
+    assert_eq!(
+        reason_of(b"a\nb", b"a b"),
+        MismatchReason::LineBreakMismatch
+    );
+
+    let mut std: &[u8] = b"ab\n";
+    let mut user: &[u8] = b"ab\n";
+    let ret = strict_compare::<Exact>(&mut std, &mut user).unwrap();
+    assert_eq!(ret.comparison, Comparison::AC);
+    assert_eq!((ret.std_consumed, ret.user_consumed), (3, 3));
+}
+
+#[test]
+fn test_strict_ignore_case() {
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            let mut std: &[u8] = $std.as_ref();
+            let mut user: &[u8] = $user.as_ref();
+
+            let ret = strict_compare::<IgnoreCase>(&mut std, &mut user).unwrap();
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"Yes\n", b"yES\n");
+    judge!(AC, b"0xABCDEF", b"0xabcdef");
+    judge!(WA, b"Yes\n", b"yES\r\n");
+    judge!(WA, b"Yes ", b"yES");
+    judge!(WA, b"[", b"{");
+}
@@ -0,0 +1,241 @@
+use super::normal::{handle_eof, poll_diff};
+use super::{byte_position, catch_io, CompareError, CompareReport, Comparison};
+use super::{ByteEq, Exact, IgnoreCase, Mismatch, MismatchReason, TextOptions};
+
+use crate::byte_read::{IoByte, TrackedRead};
+
+use std::panic::AssertUnwindSafe;
+
+pub fn try_token_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        token_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)
+}
+
+fn token_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &TextOptions,
+) -> CompareReport {
+    let mismatch = if options.ignore_case {
+        token_verdict::<IgnoreCase>(std_reader, user_reader)
+    } else {
+        token_verdict::<Exact>(std_reader, user_reader)
+    };
+    let comparison = match mismatch {
+        None => Comparison::AC,
+        Some(_) => Comparison::WA,
+    };
+    CompareReport::new(
+        comparison,
+        mismatch,
+        std_reader.position().offset,
+        user_reader.position().offset,
+    )
+}
+
+#[inline(never)]
+fn token_verdict<E: ByteEq>(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+) -> Option<Mismatch> {
+    let mut std_byte = std_reader.next_byte();
+    let mut user_byte = user_reader.next_byte();
+
+    // the last byte both sides agree on.
+    // the start of file is a separator.
+    let mut last: u8 = b' ';
+
+    macro_rules! wa {
+        ($reason:ident) => {
+            return Some(Mismatch {
+                std: byte_position(std_reader, std_byte),
+                user: byte_position(user_reader, user_byte),
+                reason: MismatchReason::$reason,
+            })
+        };
+    }
+
+    loop {
+        if std_byte.is_eof() {
+            match handle_eof(user_reader, user_byte, Comparison::AC) {
+                Comparison::AC => return None,
+                _ => wa!(ExtraOutput),
+            }
+        }
+
+        if user_byte.is_eof() {
+            match handle_eof(std_reader, std_byte, Comparison::AC) {
+                Comparison::AC => return None,
+                _ => wa!(MissingOutput),
+            }
+        }
+
+        let (a, b) = (std_byte.as_u8(), user_byte.as_u8());
+        if E::eq(a, b) {
+            last = a;
+            let ret = poll_diff::<E>(std_reader, user_reader, &mut last);
+            std_byte = ret.0;
+            user_byte = ret.1;
+            continue;
+        }
+
+        let flaga = a.is_ascii_whitespace();
+        let flagb = b.is_ascii_whitespace();
+
+        // a != b
+        // both of them are non-space
+        if !flaga & !flagb {
+            wa!(TokenMismatch);
+        }
+
+        // a != b
+        // one side continues a token which has ended on the other side
+        if (!flaga | !flagb) && !last.is_ascii_whitespace() {
+            wa!(TokenMismatch);
+        }
+
+        if flaga {
+            std_byte = poll_nonspace(std_reader);
+        }
+        if flagb {
+            user_byte = poll_nonspace(user_reader);
+        }
+        last = b' ';
+    }
+}
+
+/// poll until non-space or EOF
+#[inline(always)]
+fn poll_nonspace(reader: &mut impl TrackedRead) -> IoByte {
+    loop {
+        let b: IoByte = reader.next_byte();
+        if b.is_eof() || !b.as_u8().is_ascii_whitespace() {
+            return b;
+        }
+    }
+}
+
+#[test]
+fn test_token_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let ret = token_compare(&mut std, &mut user, &TextOptions::new());
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"", b"");
+    judge!(AC, b"", b" \r\n\t");
+    judge!(AC, b"\n\n", b"");
+    judge!(AC, b"1 2\n3 4\n", b"1\n2\n3\n4");
+    judge!(AC, b"1 2\n3 4\n", b"  1\t2 3\r\n4   ");
+    judge!(AC, b"ab  cd", b"ab cd");
+    judge!(AC, b"ab cd", b"ab\n\ncd");
+    judge!(AC, b" a", b"a");
+    judge!(
+        AC,
+        b"aaaaaaaaaaaaaaaa bbbbbbbb",
+        b"aaaaaaaaaaaaaaaa\tbbbbbbbb\n"
+    );
+    judge!(WA, b"1", b"2");
+    judge!(WA, b"1 2", b"12");
+    judge!(WA, b"12", b"1 2");
+    judge!(WA, b"12 3", b"123");
+    judge!(WA, b"1", b"1 2");
+    judge!(WA, b"1 2", b"1");
+    judge!(WA, b"1 2", b"1 ");
+    judge!(WA, b"ab", b"abc");
+    judge!(WA, b"abc", b"ab");
+}
+
+#[test]
+fn test_token_long() {
+    use crate::byte_read::ByteReader;
+
+    let judge = |std: &[u8], user: &[u8]| {
+        for &capacity in [3, 64, 8192].iter() {
+            let mut std = ByteReader::with_capacity(capacity, std);
+            let mut user = ByteReader::with_capacity(capacity, user);
+            let ret = token_compare(&mut std, &mut user, &TextOptions::new());
+            assert_eq!(ret.comparison, Comparison::WA);
+        }
+    };
+
+    // differences around the end of the first 1 KiB block
+    for offset in 1016..1048 {
+        let std = vec![b'a'; 2000];
+        let mut user = std.clone();
+        user[offset] = b'b';
+        judge(&std, &user);
+
+        // the token before the difference must not be taken as ended
+        let std = [&b" ".repeat(offset)[..], b"a b"].concat();
+        let user = [&b" ".repeat(offset)[..], b"ab"].concat();
+        judge(&std, &user);
+    }
+}
+
+#[test]
+fn test_token_report() {
+    use crate::byte_read::ByteReader;
+
+    let mut std = ByteReader::with_capacity(64, &b"1 2\n3 4"[..]);
+    let mut user = ByteReader::with_capacity(64, &b"1\n2\n3 5"[..]);
+    let ret = token_compare(&mut std, &mut user, &TextOptions::new());
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 3));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (3, 3));
+
+    let reason_of = |std: &[u8], user: &[u8]| {
+        let mut std = ByteReader::with_capacity(64, std);
+        let mut user = ByteReader::with_capacity(64, user);
+        token_compare(&mut std, &mut user, &TextOptions::new())
+            .mismatch
+            .unwrap()
+            .reason
+    };
+    assert_eq!(reason_of(b"1", b"1 2"), MismatchReason::ExtraOutput);
+    assert_eq!(reason_of(b"1 2", b"1"), MismatchReason::MissingOutput);
+}
+
+#[test]
+fn test_token_ignore_case() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            let mut options = TextOptions::new();
+            options.ignore_case = true;
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+
+                let ret = token_compare(&mut std, &mut user, &options);
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"YES NO", b"yes\nno");
+    judge!(AC, b"Case #1: Yes", b"case  #1: YES");
+    judge!(WA, b"YES NO", b"yesno");
+    judge!(WA, b"YES", b"YES!");
+}
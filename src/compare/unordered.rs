@@ -0,0 +1,1153 @@
+use super::float::within_eps;
+use super::{catch_io, CompareError, CompareReport, Comparison};
+use super::{LiteralPolicy, Mismatch, MismatchReason};
+
+use crate::byte_read::{IoByte, Position, TrackedRead};
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::mem;
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
+
+pub fn try_unordered_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &UnorderedOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        unordered_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)?
+}
+
+pub fn try_float_set_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FloatSetOptions,
+) -> Result<CompareReport, CompareError> {
+    catch_io(AssertUnwindSafe(move || {
+        float_set_compare(std_reader, user_reader, options)
+    }))
+    .map_err(CompareError::Io)?
+}
+
+/// What an element of the multisets is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnorderedBy {
+    /// Each line, with trailing whitespaces trimmed
+    Lines,
+    /// Each token of the whole output
+    Tokens,
+    /// Each token, compared line by line
+    LineTokens,
+}
+
+/// Options of unordered mode.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnorderedOptions {
+    pub by: UnorderedBy,
+    /// Treats lines which differ only in whitespaces as equal and gives PE, like normal mode.
+    /// Only affects [`UnorderedBy::Lines`].
+    pub collapse_spaces: bool,
+    /// Max bytes to hold the elements of each side in memory. `None` means no limit.
+    /// Std exceeding it is an error. User exceeding it gives WA.
+    pub max_memory: Option<usize>,
+}
+
+impl UnorderedOptions {
+    pub fn new(by: UnorderedBy) -> Self {
+        Self {
+            by,
+            collapse_spaces: false,
+            max_memory: None,
+        }
+    }
+}
+
+/// Options of float set mode.
+///
+/// Each line is split into tuples of `arity` floats, and the tuples are compared as multisets.
+/// Two tuples match if each pair of values is within `eps` or `rel_eps`, like float mode.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatSetOptions {
+    /// Floats per tuple. Zero is treated as one.
+    pub arity: usize,
+    /// Max absolute error
+    pub eps: f64,
+    /// Max relative error to std. Zero disables the relative check.
+    pub rel_eps: f64,
+    /// Max bytes to hold the tuples of each side in memory. `None` means no limit.
+    /// Std exceeding it is an error. User exceeding it gives WA.
+    pub max_memory: Option<usize>,
+}
+
+impl FloatSetOptions {
+    pub fn new(arity: usize, eps: f64) -> Self {
+        Self {
+            arity,
+            eps,
+            rel_eps: 0.0,
+            max_memory: None,
+        }
+    }
+
+    /// Whether user value `b` is close enough to std value `a`.
+    fn is_close(&self, a: f64, b: f64) -> bool {
+        within_eps(a, b, (b - a).abs(), self.eps, self.rel_eps)
+    }
+
+    /// The range of user values which may be close to std value `a`.
+    fn window(&self, a: f64) -> Option<(f64, f64)> {
+        if a.is_nan() {
+            return None;
+        }
+        if a.is_infinite() {
+            return Some((a, a));
+        }
+        let tol = self.eps.max(self.rel_eps * a.abs());
+        Some((a - tol, a + tol))
+    }
+}
+
+/// The first elements which are present in one side but not the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnorderedMismatch {
+    /// `None` if every std element is matched.
+    pub std_element: Option<String>,
+    /// `None` if every user element is matched.
+    pub user_element: Option<String>,
+}
+
+#[derive(Debug)]
+struct Element {
+    start: usize,
+    end: usize,
+    pos: Position,
+}
+
+/// Elements stored in a single arena.
+#[derive(Debug, Default)]
+struct Elements {
+    arena: Vec<u8>,
+    list: Vec<Element>,
+    /// Set on a user side, which can not match std past it
+    bound: Option<Bound>,
+}
+
+/// What std holds.
+#[derive(Debug, Clone, Copy)]
+struct Bound {
+    /// Non-empty elements after these are extra.
+    elements: usize,
+    /// `usize::MAX` if elements equal to std ones may be longer.
+    bytes: usize,
+}
+
+/// Makes room for `additional` more items, growing like `Vec` does
+/// but by no more than `room` bytes.
+fn grow<T>(vec: &mut Vec<T>, additional: usize, room: usize) -> Result<(), CompareError> {
+    let spare = vec.capacity() - vec.len();
+    if spare >= additional {
+        return Ok(());
+    }
+    let affordable = spare + room / mem::size_of::<T>();
+    if affordable < additional {
+        return Err(CompareError::TooLarge);
+    }
+    let wanted = additional.max(vec.capacity()).max(8);
+    vec.reserve_exact(wanted.min(affordable));
+    Ok(())
+}
+
+impl Elements {
+    fn get(&self, idx: usize) -> &[u8] {
+        let e = &self.list[idx];
+        &self.arena[e.start..e.end]
+    }
+
+    fn memory(&self) -> usize {
+        self.arena.capacity() + self.list.capacity() * mem::size_of::<Element>()
+    }
+
+    /// Bounds a user side by what `std` holds.
+    /// `TooLarge` is returned once the user side has gone past it.
+    fn bound_by(&mut self, std: &Elements, exact: bool) {
+        self.bound = Some(Bound {
+            elements: std.list.len(),
+            bytes: if exact { std.arena.len() } else { usize::MAX },
+        });
+    }
+
+    /// Reserves what `std` holds, which a user side matching it never outgrows.
+    fn reserve_as(&mut self, std: &Elements) {
+        self.arena.reserve_exact(std.arena.len());
+        self.list.reserve_exact(std.list.len());
+    }
+
+    /// Appends a byte of the element being read.
+    #[inline(always)]
+    fn push_byte(&mut self, byte: u8, limit: usize) -> Result<(), CompareError> {
+        if self.arena.len() == self.arena.capacity() {
+            let room = limit.saturating_sub(self.memory());
+            grow(&mut self.arena, 1, room)?;
+        }
+        self.arena.push(byte);
+        Ok(())
+    }
+
+    /// Appends bytes of the element being read.
+    fn extend(&mut self, bytes: &[u8], limit: usize) -> Result<(), CompareError> {
+        let room = limit.saturating_sub(self.memory());
+        grow(&mut self.arena, bytes.len(), room)?;
+        self.arena.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn push(&mut self, start: usize, pos: Position, limit: usize) -> Result<(), CompareError> {
+        let end = self.arena.len();
+        let room = limit.saturating_sub(self.memory());
+        grow(&mut self.list, 1, room)?;
+        self.list.push(Element { start, end, pos });
+
+        // the first element past the bound is kept to be reported
+        if let Some(bound) = self.bound {
+            if (end > start && self.list.len() > bound.elements) || end > bound.bytes {
+                return Err(CompareError::TooLarge);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.arena.clear();
+        self.list.clear();
+    }
+
+    fn sort_by(&mut self, cmp: fn(&[u8], &[u8]) -> Ordering) {
+        let Elements { arena, list, .. } = self;
+        list.sort_unstable_by(|a, b| cmp(&arena[a.start..a.end], &arena[b.start..b.end]));
+    }
+
+    fn literal(&self, idx: Option<usize>) -> Option<String> {
+        idx.map(|idx| String::from_utf8_lossy(self.get(idx)).into_owned())
+    }
+}
+
+fn cmp_exact(a: &[u8], b: &[u8]) -> Ordering {
+    a.cmp(b)
+}
+
+fn cmp_collapsed(a: &[u8], b: &[u8]) -> Ordering {
+    fn tokens(s: &[u8]) -> impl Iterator<Item = &[u8]> {
+        s.split(u8::is_ascii_whitespace).filter(|t| !t.is_empty())
+    }
+    tokens(a).cmp(tokens(b))
+}
+
+/// Compares two multisets.
+/// Returns the first unmatched element (in stream order) of both sides.
+fn diff_multisets(
+    std: &mut Elements,
+    user: &mut Elements,
+    cmp: fn(&[u8], &[u8]) -> Ordering,
+) -> (Option<usize>, Option<usize>) {
+    std.sort_by(cmp);
+    user.sort_by(cmp);
+
+    fn first(elements: &Elements, acc: &mut Option<usize>, idx: usize) {
+        let offset = elements.list[idx].pos.offset;
+        match *acc {
+            Some(prev) if elements.list[prev].pos.offset <= offset => {}
+            _ => *acc = Some(idx),
+        }
+    }
+
+    let (mut std_first, mut user_first) = (None, None);
+    let (mut i, mut j) = (0, 0);
+    while i < std.list.len() && j < user.list.len() {
+        match cmp(std.get(i), user.get(j)) {
+            Ordering::Less => {
+                first(std, &mut std_first, i);
+                i += 1;
+            }
+            Ordering::Greater => {
+                first(user, &mut user_first, j);
+                j += 1;
+            }
+            Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    for i in i..std.list.len() {
+        first(std, &mut std_first, i);
+    }
+    for j in j..user.list.len() {
+        first(user, &mut user_first, j);
+    }
+    (std_first, user_first)
+}
+
+fn unordered_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &UnorderedOptions,
+) -> Result<CompareReport, CompareError> {
+    let limit = options.max_memory.unwrap_or(usize::MAX);
+    match options.by {
+        UnorderedBy::LineTokens => compare_line_tokens(std_reader, user_reader, limit),
+        UnorderedBy::Tokens | UnorderedBy::Lines => {
+            let mut std = Elements::default();
+            let mut user = Elements::default();
+            read_all(std_reader, &mut std, options.by, limit)?;
+            let exact = !(options.collapse_spaces && options.by == UnorderedBy::Lines);
+            user.bound_by(&std, exact);
+            user.reserve_as(&std);
+            let outgrown = match read_all(user_reader, &mut user, options.by, limit) {
+                Err(CompareError::TooLarge) => true,
+                ret => ret.map(|()| false)?,
+            };
+
+            let (std_first, user_first) = diff_multisets(&mut std, &mut user, cmp_exact);
+            if std_first.is_none() && user_first.is_none() && !outgrown {
+                return Ok(report(std_reader, user_reader, None));
+            }
+
+            let pe = options.collapse_spaces && options.by == UnorderedBy::Lines;
+            if pe && !outgrown {
+                let collapsed = diff_multisets(&mut std, &mut user, cmp_collapsed);
+                if collapsed == (None, None) {
+                    let failure = Failure {
+                        std: &std,
+                        std_idx: std_first,
+                        std_pos: std_reader.position(),
+                        user: &user,
+                        user_idx: user_first,
+                        user_pos: user_reader.position(),
+                        pe: true,
+                        outgrown,
+                    };
+                    return Ok(report(std_reader, user_reader, Some(failure)));
+                }
+            }
+
+            let failure = Failure {
+                std: &std,
+                std_idx: std_first,
+                std_pos: std_reader.position(),
+                user: &user,
+                user_idx: user_first,
+                user_pos: user_reader.position(),
+                pe: false,
+                outgrown,
+            };
+            Ok(report(std_reader, user_reader, Some(failure)))
+        }
+    }
+}
+
+fn compare_line_tokens(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    limit: usize,
+) -> Result<CompareReport, CompareError> {
+    let mut std = Elements::default();
+    let mut user = Elements::default();
+    loop {
+        std.clear();
+        user.clear();
+
+        let std_pos = std_reader.position();
+        let std_line = read_line_tokens(std_reader, &mut std, limit)?;
+        let user_pos = user_reader.position();
+        user.bound_by(&std, true);
+        let (user_line, outgrown) = match read_line_tokens(user_reader, &mut user, limit) {
+            Err(CompareError::TooLarge) => (true, true),
+            ret => (ret?, false),
+        };
+
+        // a side which has ended has no more tokens
+        if !std_line && !user_line {
+            return Ok(report(std_reader, user_reader, None));
+        }
+
+        let (std_first, user_first) = diff_multisets(&mut std, &mut user, cmp_exact);
+        if std_first.is_none() && user_first.is_none() && !outgrown {
+            continue;
+        }
+
+        let failure = Failure {
+            std: &std,
+            std_idx: std_first,
+            std_pos,
+            user: &user,
+            user_idx: user_first,
+            user_pos,
+            pe: false,
+            outgrown,
+        };
+        return Ok(report(std_reader, user_reader, Some(failure)));
+    }
+}
+
+/// Unmatched elements of both sides.
+/// `std_pos` and `user_pos` are used when one side has no unmatched element.
+///
+/// An outgrown user side has gone past what std holds, or past the memory limit.
+/// It has unread elements, so it never matches std.
+struct Failure<'a> {
+    std: &'a Elements,
+    std_idx: Option<usize>,
+    std_pos: Position,
+    user: &'a Elements,
+    user_idx: Option<usize>,
+    user_pos: Position,
+    pe: bool,
+    outgrown: bool,
+}
+
+fn report(
+    std_reader: &impl TrackedRead,
+    user_reader: &impl TrackedRead,
+    failure: Option<Failure<'_>>,
+) -> CompareReport {
+    let std_consumed = std_reader.position().offset;
+    let user_consumed = user_reader.position().offset;
+
+    let f = match failure {
+        None => return CompareReport::new(Comparison::AC, None, std_consumed, user_consumed),
+        Some(f) => f,
+    };
+
+    let (comparison, reason) = match (f.pe, f.std_idx, f.user_idx) {
+        (true, _, _) => (Comparison::PE, MismatchReason::WhitespaceOnly),
+        (false, Some(_), None) if !f.outgrown => (Comparison::WA, MismatchReason::MissingOutput),
+        (false, None, _) => (Comparison::WA, MismatchReason::ExtraOutput),
+        (false, _, _) => (Comparison::WA, MismatchReason::TokenMismatch),
+    };
+    let mismatch = Mismatch {
+        std: f.std_idx.map_or(f.std_pos, |idx| f.std.list[idx].pos),
+        user: f.user_idx.map_or(f.user_pos, |idx| f.user.list[idx].pos),
+        reason,
+    };
+    let mut report = CompareReport::new(comparison, Some(mismatch), std_consumed, user_consumed);
+    report.unordered_mismatch = Some(UnorderedMismatch {
+        std_element: f.std.literal(f.std_idx),
+        user_element: f.user.literal(f.user_idx),
+    });
+    report
+}
+
+#[inline(always)]
+fn is_space(byte: IoByte) -> bool {
+    byte.as_u8().is_ascii_whitespace()
+}
+
+fn read_all(
+    reader: &mut impl TrackedRead,
+    elements: &mut Elements,
+    by: UnorderedBy,
+    limit: usize,
+) -> Result<(), CompareError> {
+    match by {
+        UnorderedBy::Tokens => while read_line_tokens(reader, elements, limit)? {},
+        UnorderedBy::Lines => {
+            while read_line(reader, elements, limit)? {}
+            // trim_end(file)
+            while let Some(e) = elements.list.last() {
+                if e.start != e.end {
+                    break;
+                }
+                elements.list.pop();
+            }
+        }
+        UnorderedBy::LineTokens => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Reads a line with trailing whitespaces trimmed.
+/// Returns false if the reader has ended.
+fn read_line(
+    reader: &mut impl TrackedRead,
+    elements: &mut Elements,
+    limit: usize,
+) -> Result<bool, CompareError> {
+    let mut byte = reader.next_byte();
+    if byte.is_eof() {
+        return Ok(false);
+    }
+
+    let pos = reader.last_position();
+    let start = elements.arena.len();
+    while !byte.is_eof() && byte.as_u8() != b'\n' {
+        elements.push_byte(byte.as_u8(), limit)?;
+        byte = reader.next_byte();
+    }
+    while let Some(b) = elements.arena.last() {
+        if elements.arena.len() == start || !b.is_ascii_whitespace() {
+            break;
+        }
+        elements.arena.pop();
+    }
+    elements.push(start, pos, limit)?;
+    Ok(true)
+}
+
+/// Reads tokens of a line.
+/// Returns false if the reader has ended.
+fn read_line_tokens(
+    reader: &mut impl TrackedRead,
+    elements: &mut Elements,
+    limit: usize,
+) -> Result<bool, CompareError> {
+    let mut byte = reader.next_byte();
+    if byte.is_eof() {
+        return Ok(false);
+    }
+
+    loop {
+        while !byte.is_eof() && byte.as_u8() != b'\n' && is_space(byte) {
+            byte = reader.next_byte();
+        }
+        if byte.is_eof() || byte.as_u8() == b'\n' {
+            return Ok(true);
+        }
+
+        let pos = reader.last_position();
+        let start = elements.arena.len();
+        while !byte.is_eof() && !is_space(byte) {
+            elements.push_byte(byte.as_u8(), limit)?;
+            byte = reader.next_byte();
+        }
+        elements.push(start, pos, limit)?;
+    }
+}
+
+/// Tuples of floats, with their literals as elements.
+#[derive(Debug)]
+struct Tuples {
+    arity: usize,
+    /// Tokens of each tuple joined by a space
+    elements: Elements,
+    /// Values of all tuples, `arity` per tuple
+    values: Vec<f64>,
+}
+
+impl Tuples {
+    fn new(arity: usize) -> Self {
+        Self {
+            arity: arity.max(1),
+            elements: Elements::default(),
+            values: Vec::new(),
+        }
+    }
+
+    fn get(&self, idx: usize) -> &[f64] {
+        &self.values[idx * self.arity..(idx + 1) * self.arity]
+    }
+
+    fn memory(&self) -> usize {
+        self.elements.memory() + self.values.capacity() * mem::size_of::<f64>()
+    }
+
+    /// Bounds a user side by the tuples of `std` and reserves what it holds.
+    fn bound_by(&mut self, std: &Tuples) {
+        self.elements.bound_by(&std.elements, false);
+        self.elements.reserve_as(&std.elements);
+        self.values.reserve_exact(std.values.len());
+    }
+
+    /// Appends token `idx` of `line` with its value, starting a new tuple if `first`.
+    /// `limit` covers both the tuples and the line.
+    fn push_token(
+        &mut self,
+        line: &Elements,
+        idx: usize,
+        first: bool,
+        value: f64,
+        limit: usize,
+    ) -> Result<(), CompareError> {
+        let elements_limit =
+            limit.saturating_sub(self.values.capacity() * mem::size_of::<f64>() + line.memory());
+        let elements = &mut self.elements;
+        if first {
+            let start = elements.arena.len();
+            elements.extend(line.get(idx), elements_limit)?;
+            elements.push(start, line.list[idx].pos, elements_limit)?;
+        } else {
+            elements.extend(b" ", elements_limit)?;
+            elements.extend(line.get(idx), elements_limit)?;
+            elements.list.last_mut().unwrap().end = elements.arena.len();
+        }
+
+        // pushed last, so that only whole tuples have all their values
+        let room = limit.saturating_sub(self.memory() + line.memory());
+        grow(&mut self.values, 1, room)?;
+        self.values.push(value);
+        Ok(())
+    }
+
+    /// Drops the tuple being read when an error stopped it.
+    fn drop_partial(&mut self) {
+        let len = self.values.len() / self.arity;
+        self.elements.list.truncate(len);
+        self.values.truncate(len * self.arity);
+    }
+
+    fn len(&self) -> usize {
+        self.elements.list.len()
+    }
+}
+
+/// Reads all tuples.
+/// Returns the position of the first token which is not a float or does not fill a tuple.
+fn read_tuples(
+    reader: &mut impl TrackedRead,
+    tuples: &mut Tuples,
+    limit: usize,
+) -> Result<Option<Position>, CompareError> {
+    let arity = tuples.arity;
+    let policy = LiteralPolicy::new();
+    let mut line = Elements::default();
+    loop {
+        line.clear();
+        if !read_line_tokens(reader, &mut line, limit.saturating_sub(tuples.memory()))? {
+            return Ok(None);
+        }
+        for (idx, e) in line.list.iter().enumerate() {
+            if line.list.len() - idx < arity - idx % arity {
+                return Ok(Some(e.pos));
+            }
+            match policy.parse(line.get(idx)) {
+                Ok(x) => tuples.push_token(&line, idx, idx % arity == 0, x, limit)?,
+                Err(()) => return Ok(Some(e.pos)),
+            }
+        }
+    }
+}
+
+/// Finds tuples which may be close to std tuples.
+struct Candidates<'a> {
+    std: &'a Tuples,
+    user: &'a Tuples,
+    options: &'a FloatSetOptions,
+    /// User tuples sorted by the first value, except those starting with NaN
+    order: Vec<usize>,
+    /// The first value of each tuple in `order`
+    keys: Vec<f64>,
+}
+
+impl<'a> Candidates<'a> {
+    fn new(std: &'a Tuples, user: &'a Tuples, options: &'a FloatSetOptions) -> Self {
+        let first = |j: usize| user.get(j)[0];
+        // NaN is never close to anything
+        let mut order: Vec<usize> = (0..user.len()).filter(|&j| !first(j).is_nan()).collect();
+        order.sort_unstable_by(|&a, &b| first(a).total_cmp(&first(b)));
+        let keys = order.iter().map(|&j| first(j)).collect();
+        Self {
+            std,
+            user,
+            options,
+            order,
+            keys,
+        }
+    }
+
+    /// Positions in `order` whose first value is close to std tuple `i`.
+    fn window(&self, i: usize) -> Range<usize> {
+        let (lo, hi) = self
+            .options
+            .window(self.std.get(i)[0])
+            .unwrap_or((1.0, 0.0));
+        let start = self.keys.partition_point(|&k| k < lo);
+        let end = start + self.keys[start..].partition_point(|&k| k <= hi);
+        start..end
+    }
+
+    fn is_close(&self, i: usize, j: usize) -> bool {
+        let (a, b) = (self.std.get(i), self.user.get(j));
+        a.iter().zip(b).all(|(&a, &b)| self.options.is_close(a, b))
+    }
+
+    /// User tuples which are close to std tuple `i`.
+    fn of(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.order[self.window(i)]
+            .iter()
+            .copied()
+            .filter(move |&j| self.is_close(i, j))
+    }
+}
+
+/// Skips positions which are already taken, with path compression.
+struct NextFree(Vec<usize>);
+
+impl NextFree {
+    fn new(len: usize) -> Self {
+        Self((0..=len).collect())
+    }
+
+    /// The first free position not before `p`, or `len` if there is none.
+    fn find(&mut self, p: usize) -> usize {
+        let mut root = p;
+        while self.0[root] != root {
+            root = self.0[root];
+        }
+        let mut p = p;
+        while p != root {
+            p = mem::replace(&mut self.0[p], root);
+        }
+        root
+    }
+
+    fn take(&mut self, p: usize) {
+        self.0[p] = p + 1;
+    }
+}
+
+/// Numbers std tuples so that identical tuples share a group.
+fn group_tuples(tuples: &Tuples) -> (Vec<usize>, usize) {
+    let cmp = |a: &usize, b: &usize| {
+        let (a, b) = (tuples.get(*a), tuples.get(*b));
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| a.total_cmp(b))
+            .find(|&ord| ord != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    };
+    let mut order: Vec<usize> = (0..tuples.len()).collect();
+    order.sort_unstable_by(cmp);
+    let mut groups = vec![0; tuples.len()];
+    let mut count = 0;
+    for (k, &i) in order.iter().enumerate() {
+        if k > 0 && cmp(&order[k - 1], &i) != Ordering::Equal {
+            count += 1;
+        }
+        groups[i] = count;
+    }
+    (groups, count + 1)
+}
+
+/// Finds a maximum matching between std and user tuples where matched tuples are close.
+/// Returns the first unmatched tuple (in stream order) of both sides.
+fn match_tuples(
+    std: &Tuples,
+    user: &Tuples,
+    options: &FloatSetOptions,
+) -> (Option<usize>, Option<usize>) {
+    const NONE: usize = usize::MAX;
+
+    let candidates = Candidates::new(std, user, options);
+    let mut std_match = vec![NONE; std.len()];
+    let mut user_match = vec![NONE; user.len()];
+
+    // most tuples have only one candidate; matched ones are skipped for good
+    let mut free = NextFree::new(candidates.order.len());
+    for (i, m) in std_match.iter_mut().enumerate() {
+        let window = candidates.window(i);
+        let mut p = free.find(window.start);
+        while p < window.end {
+            let j = candidates.order[p];
+            if candidates.is_close(i, j) {
+                *m = j;
+                user_match[j] = i;
+                free.take(p);
+                break;
+            }
+            p = free.find(p + 1);
+        }
+    }
+
+    // augmenting paths by BFS
+    // identical std tuples have the same candidates, so each group is expanded once per
+    // search, and a group whose search failed fails again (a tuple without an augmenting
+    // path never gets one by later augmentations)
+    let (groups, group_count) = group_tuples(std);
+    let mut expanded = vec![0_u32; group_count];
+    let mut failed = vec![false; group_count];
+    let mut visited = vec![0_u32; user.len()];
+    let mut from = vec![NONE; user.len()];
+    let mut queue = VecDeque::new();
+    let mut round = 0;
+    for root in 0..std.len() {
+        if std_match[root] != NONE || failed[groups[root]] {
+            continue;
+        }
+        round += 1;
+        queue.clear();
+        queue.push_back(root);
+        failed[groups[root]] = true;
+        'bfs: while let Some(i) = queue.pop_front() {
+            if mem::replace(&mut expanded[groups[i]], round) == round {
+                continue;
+            }
+            for j in candidates.of(i) {
+                if visited[j] == round {
+                    continue;
+                }
+                visited[j] = round;
+                from[j] = i;
+                if user_match[j] != NONE {
+                    queue.push_back(user_match[j]);
+                    continue;
+                }
+                let mut j = j;
+                loop {
+                    let i = from[j];
+                    let next = mem::replace(&mut std_match[i], j);
+                    user_match[j] = i;
+                    if i == root {
+                        failed[groups[root]] = false;
+                        break 'bfs;
+                    }
+                    j = next;
+                }
+            }
+        }
+    }
+
+    let first = |matches: &[usize]| matches.iter().position(|&m| m == NONE);
+    (first(&std_match), first(&user_match))
+}
+
+fn float_set_compare(
+    std_reader: &mut impl TrackedRead,
+    user_reader: &mut impl TrackedRead,
+    options: &FloatSetOptions,
+) -> Result<CompareReport, CompareError> {
+    let limit = options.max_memory.unwrap_or(usize::MAX);
+
+    let mut std = Tuples::new(options.arity);
+    let mut user = Tuples::new(options.arity);
+    let mut invalid = read_tuples(std_reader, &mut std, limit)?.map(|pos| (pos, true));
+    let mut outgrown = false;
+    if invalid.is_none() {
+        user.bound_by(&std);
+        invalid = match read_tuples(user_reader, &mut user, limit) {
+            Err(CompareError::TooLarge) => {
+                user.drop_partial();
+                outgrown = true;
+                None
+            }
+            ret => ret?.map(|pos| (pos, false)),
+        };
+    }
+    if let Some((pos, is_std)) = invalid {
+        let mismatch = Mismatch {
+            std: if is_std { pos } else { std_reader.position() },
+            user: if is_std { user_reader.position() } else { pos },
+            reason: MismatchReason::InvalidToken,
+        };
+        return Ok(CompareReport::new(
+            Comparison::WA,
+            Some(mismatch),
+            std_reader.position().offset,
+            user_reader.position().offset,
+        ));
+    }
+
+    let (std_first, user_first) = match_tuples(&std, &user, options);
+    if std_first.is_none() && user_first.is_none() && !outgrown {
+        return Ok(report(std_reader, user_reader, None));
+    }
+    let failure = Failure {
+        std: &std.elements,
+        std_idx: std_first,
+        std_pos: std_reader.position(),
+        user: &user.elements,
+        user_idx: user_first,
+        user_pos: user_reader.position(),
+        pe: false,
+        outgrown,
+    };
+    Ok(report(std_reader, user_reader, Some(failure)))
+}
+
+#[cfg(test)]
+fn judge_unordered(options: &UnorderedOptions, std: &[u8], user: &[u8]) -> CompareReport {
+    use crate::byte_read::ByteReader;
+
+    let mut std = ByteReader::with_capacity(16, std);
+    let mut user = ByteReader::with_capacity(16, user);
+    unordered_compare(&mut std, &mut user, options).unwrap()
+}
+
+#[test]
+fn test_unordered_comparer() {
+    macro_rules! judge {
+        ($ret:expr, $by:ident, $std:expr,$user:expr) => {{
+            let options = UnorderedOptions::new(UnorderedBy::$by);
+            let ret = judge_unordered(&options, $std.as_ref(), $user.as_ref());
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, Lines, b"", b"");
+    judge!(AC, Lines, b"a\nb\nc\n", b"c\r\nb\na");
+    judge!(AC, Lines, b"a b\nc\n", b"c  \na b\n\n\n");
+    judge!(AC, Lines, b"a\na\nb", b"a\nb\na");
+    judge!(AC, Lines, b"a\n\nb", b"\nb\na");
+    judge!(WA, Lines, b"a\na\nb", b"a\nb\nb");
+    judge!(WA, Lines, b"a\nb", b"a\nb\nc");
+    judge!(WA, Lines, b"a\nb\nc", b"a\nb");
+    judge!(WA, Lines, b"a b\n", b"a  b\n");
+    judge!(WA, Lines, b"a b\n", b"b a\n");
+
+    judge!(AC, Tokens, b"", b" \n ");
+    judge!(AC, Tokens, b"1 2 3\n4", b"4 3\n2\t1\n");
+    judge!(AC, Tokens, b"1 1 2", b"1 2 1");
+    judge!(WA, Tokens, b"1 1 2", b"1 2 2");
+    judge!(WA, Tokens, b"1 2", b"12");
+    judge!(WA, Tokens, b"1 2", b"1 2 3");
+
+    judge!(AC, LineTokens, b"1 2\n3 4\n", b"2 1\n4  3");
+    judge!(AC, LineTokens, b"1 2\n\n3\n", b"2 1\n\n3\n\n");
+    judge!(WA, LineTokens, b"1 2\n3 4\n", b"1 3\n2 4\n");
+    judge!(WA, LineTokens, b"1 2\n3 4\n", b"2 1 3 4\n");
+    judge!(WA, LineTokens, b"1\n\n2", b"1\n2");
+    judge!(WA, LineTokens, b"1\n2", b"1\n2\n3");
+    judge!(WA, LineTokens, b"1\n2\n3", b"1\n2");
+}
+
+#[test]
+fn test_unordered_collapse_spaces() {
+    macro_rules! judge {
+        ($ret:expr, $std:expr,$user:expr) => {{
+            let mut options = UnorderedOptions::new(UnorderedBy::Lines);
+            options.collapse_spaces = true;
+            let ret = judge_unordered(&options, $std.as_ref(), $user.as_ref());
+            assert_eq!(ret.comparison, $ret);
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, b"a b\nc\n", b"c\na b\n");
+    judge!(PE, b"a b\nc\n", b"c\na  b\n");
+    judge!(PE, b"a b\nc\n", b"c\n a\tb\n");
+    judge!(WA, b"a b\nc\n", b"c\nab\n");
+}
+
+#[test]
+fn test_unordered_report() {
+    let options = UnorderedOptions::new(UnorderedBy::Lines);
+    let ret = judge_unordered(&options, b"a\nb\nc\nd\n", b"d\nx\nb\na\ny\n");
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!(mismatch.std.line, 3);
+    assert_eq!(mismatch.user.line, 2);
+    let m = ret.unordered_mismatch.unwrap();
+    assert_eq!(m.std_element.as_deref(), Some("c"));
+    assert_eq!(m.user_element.as_deref(), Some("x"));
+
+    let options = UnorderedOptions::new(UnorderedBy::Tokens);
+    let ret = judge_unordered(&options, b"1 2", b"2 1 3");
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::ExtraOutput);
+    let m = ret.unordered_mismatch.unwrap();
+    assert_eq!(m.std_element, None);
+    assert_eq!(m.user_element.as_deref(), Some("3"));
+
+    let options = UnorderedOptions::new(UnorderedBy::LineTokens);
+    let ret = judge_unordered(&options, b"1 2\n3 4\n", b"2 1\n4 5\n");
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!((mismatch.std.line, mismatch.std.column), (2, 1));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (2, 3));
+}
+
+#[test]
+fn test_unordered_max_memory() {
+    use crate::byte_read::ByteReader;
+
+    let mut options = UnorderedOptions::new(UnorderedBy::Tokens);
+    options.max_memory = Some(256);
+
+    let std = b"1 ".repeat(100);
+    let mut std = ByteReader::with_capacity(16, &std[..]);
+    let mut user = ByteReader::with_capacity(16, &b"1"[..]);
+    let ret = unordered_compare(&mut std, &mut user, &options);
+    assert!(matches!(ret, Err(CompareError::TooLarge)));
+
+    for &by in [
+        UnorderedBy::Lines,
+        UnorderedBy::Tokens,
+        UnorderedBy::LineTokens,
+    ]
+    .iter()
+    {
+        // a single huge user line gives WA before it is fully buffered
+        let mut options = UnorderedOptions::new(by);
+        options.max_memory = Some(256);
+        let user = vec![b'x'; 1 << 20];
+        let mut std = ByteReader::with_capacity(16, &b"x"[..]);
+        let mut user = ByteReader::with_capacity(16, &user[..]);
+        let ret = unordered_compare(&mut std, &mut user, &options).unwrap();
+        assert_eq!(ret.comparison, Comparison::WA);
+        assert!(user.position().offset <= 512);
+
+        // so do many user lines, even without a limit
+        let options = UnorderedOptions::new(by);
+        let user = b"x\n".repeat(100_000);
+        let mut std = ByteReader::with_capacity(16, &b"x\nx\nx\n"[..]);
+        let mut user = ByteReader::with_capacity(16, &user[..]);
+        let ret = unordered_compare(&mut std, &mut user, &options).unwrap();
+        assert_eq!(ret.comparison, Comparison::WA);
+        assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::ExtraOutput);
+        assert!(user.position().offset <= 64);
+
+        // the user side has a budget of its own
+        let mut options = UnorderedOptions::new(by);
+        options.max_memory = Some(65536);
+        let file = format!("{}\n", "x".repeat(99)).repeat(400);
+        let ret = judge_unordered(&options, file.as_ref(), file.as_ref());
+        assert_eq!(ret.comparison, Comparison::AC);
+    }
+
+    // trailing whitespaces and empty lines are not counted
+    let mut options = UnorderedOptions::new(UnorderedBy::Lines);
+    options.max_memory = Some(4096);
+    let ret = judge_unordered(&options, b"a\nb\n", b"b  \n\na\t\n\n\n");
+    assert_eq!(ret.comparison, Comparison::WA);
+    let ret = judge_unordered(&options, b"a\nb\n", b"b  \na\t\n\n\n");
+    assert_eq!(ret.comparison, Comparison::AC);
+}
+
+#[test]
+fn test_float_set_comparer() {
+    use crate::byte_read::ByteReader;
+
+    macro_rules! judge {
+        ($ret:expr, $arity:expr, $eps:expr, $std:expr,$user:expr) => {{
+            let options = FloatSetOptions::new($arity, $eps);
+            for &capacity in [1, 3, 64].iter() {
+                let mut std = ByteReader::with_capacity(capacity, $std.as_ref());
+                let mut user = ByteReader::with_capacity(capacity, $user.as_ref());
+                let ret = float_set_compare(&mut std, &mut user, &options).unwrap();
+                assert_eq!(ret.comparison, $ret);
+            }
+        }};
+    }
+
+    use Comparison::*;
+
+    judge!(AC, 2, 1e-6, b"", b"");
+    judge!(AC, 2, 1e-6, b"0 0\n1 2\n", b"1.0000001 2\n0 -0\n");
+    judge!(AC, 2, 1e-6, b"0 0 1 2\n", b"1 2\n0 0");
+    judge!(AC, 1, 1e-6, b"3 1 2", b"1 2 3");
+    judge!(AC, 3, 0.0, b"1 2 3\n1 2 3\n", b"1 2 3\n1 2 3\n");
+    judge!(WA, 3, 0.0, b"1 2 3\n1 2 3\n", b"1 2 3\n1 2 4\n");
+    judge!(WA, 2, 1e-6, b"0 0\n1 2\n", b"0 0\n2 1\n");
+    judge!(WA, 2, 1e-6, b"0 0\n1 2\n", b"0 0\n");
+    judge!(WA, 2, 1e-6, b"0 0\n", b"0 0\n0 0\n");
+    judge!(WA, 2, 1e-6, b"0 0\n", b"0 0 0\n");
+    judge!(WA, 2, 1e-6, b"0 0\n", b"0\n0\n");
+    judge!(WA, 2, 1e-6, b"0 0\n", b"0 x\n");
+    judge!(WA, 1, 1e-6, b"nan", b"nan");
+    judge!(AC, 1, 1e-6, b"inf -inf", b"-inf inf");
+    judge!(WA, 1, 1e-6, b"inf", b"1e308");
+
+    // greedy matching of 0.5 to 0 would leave 0 unmatched
+    judge!(AC, 1, 0.5, b"0.5 0", b"0 1");
+    judge!(WA, 1, 0.5, b"0.5 0", b"0 1.1");
+    judge!(AC, 1, 0.5, b"0 0.5 1", b"1 0.5 0");
+    judge!(
+        AC,
+        2,
+        0.5,
+        b"0 0\n0.4 0.4\n0.8 0.8\n",
+        b"0.5 0.5\n0.9 0.9\n0.1 0.1\n"
+    );
+
+    // identical tuples are interchangeable, but still need augmenting paths
+    judge!(AC, 1, 0.5, b"0.5 0.5 0", b"0 0 1");
+    judge!(WA, 1, 0.5, b"0.5 0.5 0 0", b"0 0 1 2");
+    let many = "0 0\n".repeat(10000);
+    judge!(AC, 2, 1e-6, many, many);
+    let one_off = format!("{}1 1\n", "0 0\n".repeat(9999));
+    judge!(WA, 2, 1e-6, many, one_off);
+}
+
+#[test]
+fn test_float_set_rel_eps() {
+    let mut options = FloatSetOptions::new(2, 0.0);
+    options.rel_eps = 1e-6;
+
+    let judge = |std: &[u8], user: &[u8]| {
+        use crate::byte_read::ByteReader;
+
+        let mut std = ByteReader::with_capacity(16, std);
+        let mut user = ByteReader::with_capacity(16, user);
+        float_set_compare(&mut std, &mut user, &options)
+            .unwrap()
+            .comparison
+    };
+
+    assert_eq!(judge(b"1e12 1\n", b"1000000000100 1\n"), Comparison::AC);
+    assert_eq!(judge(b"1e12 1\n", b"1000001000100 1\n"), Comparison::WA);
+    assert_eq!(judge(b"1e12 1\n", b"1e12 1.01\n"), Comparison::WA);
+}
+
+#[test]
+fn test_float_set_report() {
+    use crate::byte_read::ByteReader;
+
+    let report_of = |std: &[u8], user: &[u8]| {
+        let options = FloatSetOptions::new(2, 1e-3);
+        let mut std = ByteReader::with_capacity(16, std);
+        let mut user = ByteReader::with_capacity(16, user);
+        float_set_compare(&mut std, &mut user, &options).unwrap()
+    };
+
+    let ret = report_of(b"1 2  3 4\n5 6\n", b"5.0001 6\n1 2\n3.5\t4\n");
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::TokenMismatch);
+    assert_eq!((mismatch.std.line, mismatch.std.column), (1, 6));
+    assert_eq!((mismatch.user.line, mismatch.user.column), (3, 1));
+    let m = ret.unordered_mismatch.unwrap();
+    assert_eq!(m.std_element.as_deref(), Some("3 4"));
+    assert_eq!(m.user_element.as_deref(), Some("3.5 4"));
+
+    let ret = report_of(b"1 2\n", b"1 2\n3 4\n");
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::ExtraOutput);
+    let ret = report_of(b"1 2\n3 4\n", b"3 4\n");
+    assert_eq!(ret.mismatch.unwrap().reason, MismatchReason::MissingOutput);
+
+    let ret = report_of(b"1 2\n3 4\n", b"1 2\n3\n4\n");
+    assert_eq!(ret.unordered_mismatch, None);
+    let mismatch = ret.mismatch.unwrap();
+    assert_eq!(mismatch.reason, MismatchReason::InvalidToken);
+    assert_eq!((mismatch.user.line, mismatch.user.column), (2, 1));
+
+    let options = {
+        let mut options = FloatSetOptions::new(2, 1e-3);
+        options.max_memory = Some(64);
+        options
+    };
+    let mut std = ByteReader::with_capacity(16, &b"1 2\n3 4\n5 6\n7 8\n"[..]);
+    let mut user = ByteReader::with_capacity(16, &b""[..]);
+    assert!(matches!(
+        float_set_compare(&mut std, &mut user, &options),
+        Err(CompareError::TooLarge)
+    ));
+
+    // a user side past std gives WA
+    let options = {
+        let mut options = FloatSetOptions::new(2, 1e-3);
+        options.max_memory = Some(4096);
+        options
+    };
+    let many = b"1 2\n".repeat(100_000);
+    let huge = b"1 2 ".repeat(100_000);
+    for user in [&many, &huge].iter() {
+        let mut std = ByteReader::with_capacity(16, &b"1 2\n"[..]);
+        let mut user = ByteReader::with_capacity(16, &user[..]);
+        let ret = float_set_compare(&mut std, &mut user, &options).unwrap();
+        assert_eq!(ret.comparison, Comparison::WA);
+        assert!(user.position().offset <= 8192);
+    }
+}
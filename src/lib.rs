@@ -3,9 +3,18 @@
 mod byte_read;
 mod compare;
 
-pub use byte_read::{ByteRead, ByteReader};
-pub use compare::Comparison;
-pub use compare::{try_float_compare, try_normal_compare, try_strict_compare};
+pub use byte_read::{ByteRead, ByteReader, Position, TrackedRead};
+pub use compare::TextOptions;
+pub use compare::{try_embedded_compare, EmbeddedOptions};
+pub use compare::{try_float_compare, try_line_compare, try_normal_compare};
+pub use compare::{try_float_set_compare, FloatSetOptions};
+pub use compare::{try_fraction_compare, FractionMismatch, FractionOptions};
+pub use compare::{try_number_compare, Decimal, NumberMismatch, NumberOptions};
+pub use compare::{try_strict_compare, try_token_compare};
+pub use compare::{try_unordered_compare, UnorderedBy, UnorderedMismatch, UnorderedOptions};
+pub use compare::{Columns, FloatNorm, LiteralPolicy, Norm, SigFigs};
+pub use compare::{CompareError, CompareReport, Comparison, Mismatch, MismatchReason};
+pub use compare::{Decimals, FloatError, FloatMismatch, FloatOptions, FloatStats};
 
 #[cfg(unix)]
 pub use byte_read::unix::UnixFdReader;
@@ -1,17 +1,27 @@
 #[cfg(not(target_os = "linux"))]
 compile_error!("ojcmp does not support this platform now");
 
-use ojcmp::{ByteReader, Comparison};
+use ojcmp::TextOptions;
+use ojcmp::{ByteReader, CompareError, CompareReport, Comparison, MismatchReason, Position};
+use ojcmp::{
+    Columns, Decimals, FloatError, FloatMismatch, FloatOptions, FloatStats, LiteralPolicy,
+};
+use ojcmp::{Decimal, NumberMismatch, NumberOptions};
+use ojcmp::{EmbeddedOptions, FractionMismatch, FractionOptions};
+use ojcmp::{FloatNorm, Norm, SigFigs};
+use ojcmp::{FloatSetOptions, UnorderedBy, UnorderedMismatch, UnorderedOptions};
 
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
 use std::os::unix::prelude::FromRawFd;
 use std::path::PathBuf;
 use std::process;
+use std::str::FromStr;
 use structopt::clap::ArgGroup;
 use structopt::StructOpt;
 
 use anyhow::{Context, Result};
+use serde_json::{json, Value};
 
 #[derive(Debug, StructOpt)]
 #[structopt(author)]
@@ -20,21 +30,406 @@ enum Opts {
     Normal {
         #[structopt(flatten)]
         common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        text_opts: TextOpts,
+    },
+    /// Line compare
+    Line {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        text_opts: TextOpts,
+    },
+    /// Token compare
+    Token {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        text_opts: TextOpts,
     },
     /// Strict compare
     Strict {
         #[structopt(flatten)]
         common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        text_opts: TextOpts,
     },
     /// Float compare
     Float {
         #[structopt(flatten)]
         common_opts: CommonOpts,
 
-        #[structopt(name = "eps", short = "e", long)]
-        /// Eps for float comparing
-        eps: f64,
+        #[structopt(flatten)]
+        float_opts: FloatOpts,
+    },
+    /// Exact number compare
+    Number {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        number_opts: NumberOpts,
+    },
+    /// Embedded number compare
+    Embedded {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        embedded_opts: EmbeddedOpts,
     },
+    /// Fraction compare
+    Fraction {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        fraction_opts: FractionOpts,
+    },
+    /// Unordered compare
+    Unordered {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        unordered_opts: UnorderedOpts,
+    },
+    /// Unordered compare of float tuples
+    FloatSet {
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+
+        #[structopt(flatten)]
+        float_set_opts: FloatSetOpts,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct TextOpts {
+    /// Compares ascii letters case-insensitively
+    #[structopt(short = "i", long)]
+    ignore_case: bool,
+}
+
+impl TextOpts {
+    fn to_options(&self) -> TextOptions {
+        let mut options = TextOptions::new();
+        options.ignore_case = self.ignore_case;
+        options
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(group(ArgGroup::with_name("tolerance").args(&["eps", "rel-eps", "max-ulps", "eps-columns", "sig-figs"]).required(true).multiple(true)))]
+struct FloatOpts {
+    #[structopt(name = "eps", short = "e", long)]
+    /// Eps for float comparing, or "auto" for half a unit in the last digit of each std token
+    eps: Option<EpsOpt>,
+
+    /// Relative eps for float comparing. A token passes if either eps is satisfied
+    #[structopt(long)]
+    rel_eps: Option<f64>,
+
+    /// Max distance in units in the last place of std value
+    #[structopt(long, value_name = "n")]
+    max_ulps: Option<u64>,
+
+    /// Eps of each column, used instead of --eps. Columns cycle if a line has more tokens
+    #[structopt(long, use_delimiter = true, value_name = "eps,...")]
+    eps_columns: Vec<f64>,
+
+    /// Compares values by circular distance modulo the period, "rad" for 2π or "deg" for 360
+    #[structopt(long, value_name = "period")]
+    period: Option<PeriodOpt>,
+
+    /// Period of each column, used instead of --period. "none" for columns which are not periodic
+    #[structopt(long, use_delimiter = true, value_name = "period,...")]
+    period_columns: Vec<PeriodOpt>,
+
+    /// Whether columns restart at each std line or follow the token index
+    #[structopt(
+        long,
+        default_value = "line",
+        possible_values = &["line", "token"],
+        value_name = "by"
+    )]
+    columns_by: ColumnsBy,
+
+    /// Checks the norm of all errors against --eps and --rel-eps at EOF instead of each token
+    #[structopt(
+        long,
+        possible_values = &["l2", "linf"],
+        value_name = "norm",
+        conflicts_with_all = &["max-ulps", "eps-columns", "sig-figs"]
+    )]
+    norm: Option<NormOpt>,
+
+    /// Compares all tokens and reports error statistics
+    #[structopt(long)]
+    stats: bool,
+
+    /// Compares integers and words exactly, and only floats with eps
+    #[structopt(long)]
+    mixed: bool,
+
+    /// Gives PE if all tokens pass but line breaks or whitespaces differ, like normal mode
+    #[structopt(long)]
+    lines: bool,
+
+    /// Required digits after the decimal point of user floats, or "std" for the same as std
+    #[structopt(long, value_name = "k")]
+    decimals: Option<DecimalsOpt>,
+
+    /// A token also passes if both values are equal after rounding to n significant digits
+    #[structopt(long, value_name = "n")]
+    sig_figs: Option<usize>,
+
+    /// Minimum significant digits of user floats
+    #[structopt(long, value_name = "n", conflicts_with = "sig-figs")]
+    min_sig_figs: Option<usize>,
+
+    /// Verdict for floats with wrong decimal places or too few significant digits
+    #[structopt(
+        long,
+        default_value = "pe",
+        possible_values = &["pe", "wa"],
+        value_name = "verdict"
+    )]
+    decimals_verdict: Verdict,
+
+    #[structopt(flatten)]
+    literal_opts: LiteralOpts,
+}
+
+#[derive(Debug, StructOpt)]
+struct LiteralOpts {
+    /// Rejects "nan", "inf" and "infinity"
+    #[structopt(long)]
+    no_nan_inf: bool,
+
+    /// Treats NaN as equal to NaN
+    #[structopt(long)]
+    nan_eq: bool,
+
+    /// Rejects exponent notation like "1e9"
+    #[structopt(long)]
+    no_exponent: bool,
+
+    /// Rejects a leading '+'
+    #[structopt(long)]
+    no_plus_sign: bool,
+
+    /// Accepts hex floats like "0x1.8p3"
+    #[structopt(long)]
+    hex_float: bool,
+
+    /// Accepts ',' as the decimal separator
+    #[structopt(long)]
+    decimal_comma: bool,
+}
+
+impl LiteralOpts {
+    fn to_policy(&self) -> LiteralPolicy {
+        let mut policy = LiteralPolicy::new();
+        policy.nan_inf = !self.no_nan_inf;
+        policy.nan_eq = self.nan_eq;
+        policy.exponent = !self.no_exponent;
+        policy.plus_sign = !self.no_plus_sign;
+        policy.hex = self.hex_float;
+        policy.comma = self.decimal_comma;
+        policy
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ColumnsBy(Columns);
+
+impl FromStr for ColumnsBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "line" => Ok(ColumnsBy(Columns::Line)),
+            "token" => Ok(ColumnsBy(Columns::Token)),
+            _ => anyhow::bail!("unknown columns: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum EpsOpt {
+    Auto,
+    Value(f64),
+}
+
+impl FromStr for EpsOpt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(EpsOpt::Auto),
+            _ => match s.parse() {
+                Ok(eps) => Ok(EpsOpt::Value(eps)),
+                Err(_) => anyhow::bail!("invalid eps: {}", s),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PeriodOpt(Option<f64>);
+
+impl FromStr for PeriodOpt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(PeriodOpt(None)),
+            "rad" => Ok(PeriodOpt(Some(std::f64::consts::TAU))),
+            "deg" => Ok(PeriodOpt(Some(360.0))),
+            _ => match s.parse::<f64>() {
+                Ok(p) if p > 0.0 && p.is_finite() => Ok(PeriodOpt(Some(p))),
+                _ => anyhow::bail!("invalid period: {}", s),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NormOpt(Norm);
+
+impl FromStr for NormOpt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "l2" => Ok(NormOpt(Norm::L2)),
+            "linf" => Ok(NormOpt(Norm::LInf)),
+            _ => anyhow::bail!("unknown norm: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DecimalsOpt(Decimals);
+
+impl FromStr for DecimalsOpt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "std" => Ok(DecimalsOpt(Decimals::SameAsStd)),
+            _ => match s.parse() {
+                Ok(k) => Ok(DecimalsOpt(Decimals::Exactly(k))),
+                Err(_) => anyhow::bail!("invalid decimals: {}", s),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Verdict(Comparison);
+
+impl FromStr for Verdict {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pe" => Ok(Verdict(Comparison::PE)),
+            "wa" => Ok(Verdict(Comparison::WA)),
+            _ => anyhow::bail!("unknown verdict: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct NumberOpts {
+    /// Max absolute difference as an exact decimal, like "0.001"
+    #[structopt(long, value_name = "decimal")]
+    tolerance: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(group(ArgGroup::with_name("tolerance").args(&["eps", "rel-eps"]).required(true).multiple(true)))]
+struct EmbeddedOpts {
+    #[structopt(name = "eps", short = "e", long)]
+    /// Eps for float comparing
+    eps: Option<f64>,
+
+    /// Relative eps for float comparing. A number passes if either eps is satisfied
+    #[structopt(long)]
+    rel_eps: Option<f64>,
+
+    /// Max bytes of each current line to hold in memory
+    #[structopt(long, default_value = "1073741824", value_name = "bytes")]
+    max_memory: usize,
+}
+
+#[derive(Debug, StructOpt)]
+struct FractionOpts {
+    /// Requires user fractions in lowest terms
+    #[structopt(long)]
+    reduced: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct UnorderedOpts {
+    /// Compares lines, tokens or tokens within each line as multisets
+    #[structopt(
+        long,
+        default_value = "lines",
+        possible_values = &["lines", "tokens", "line-tokens"],
+        value_name = "unit"
+    )]
+    by: By,
+
+    /// Treats lines which differ only in whitespaces as PE (lines only)
+    #[structopt(long)]
+    collapse_spaces: bool,
+
+    /// Max memory (in bytes) to hold the elements of each file
+    #[structopt(long, default_value = "1073741824", value_name = "bytes")]
+    max_memory: usize,
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(group(ArgGroup::with_name("tolerance").args(&["eps", "rel-eps"]).required(true).multiple(true)))]
+struct FloatSetOpts {
+    /// Floats per tuple. Each line is split into tuples
+    #[structopt(long, default_value = "1", value_name = "n")]
+    arity: usize,
+
+    #[structopt(name = "eps", short = "e", long)]
+    /// Eps for float comparing
+    eps: Option<f64>,
+
+    /// Relative eps for float comparing. A value passes if either eps is satisfied
+    #[structopt(long)]
+    rel_eps: Option<f64>,
+
+    /// Max memory (in bytes) to hold the tuples of each file
+    #[structopt(long, default_value = "1073741824", value_name = "bytes")]
+    max_memory: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct By(UnorderedBy);
+
+impl FromStr for By {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lines" => Ok(By(UnorderedBy::Lines)),
+            "tokens" => Ok(By(UnorderedBy::Tokens)),
+            "line-tokens" => Ok(By(UnorderedBy::LineTokens)),
+            _ => anyhow::bail!("unknown unit: {}", s),
+        }
+    }
 }
 
 #[derive(Debug, StructOpt)]
@@ -70,6 +465,38 @@ struct CommonOpts {
     /// No output printed to stdout or stderr
     #[structopt(short = "q", long)]
     quiet: bool,
+
+    /// Output format of the result
+    #[structopt(
+        short = "f",
+        long,
+        default_value = "text",
+        possible_values = &["text", "json"],
+        value_name = "format"
+    )]
+    format: Format,
+
+    /// Writes the result to a file instead of stdout
+    #[structopt(long, value_name = "path")]
+    result_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => anyhow::bail!("unknown format: {}", s),
+        }
+    }
 }
 
 fn open(common_opts: &CommonOpts) -> anyhow::Result<(File, File)> {
@@ -98,6 +525,29 @@ fn open(common_opts: &CommonOpts) -> anyhow::Result<(File, File)> {
     Ok((std_file, user_file))
 }
 
+/// Returns a second handle of `file` if it can be read again from the start,
+/// together with the offset where reading starts.
+/// A passed fd may have been read before, so the offset is not always 0.
+fn rescan_handle(file: &File) -> Result<Option<(File, u64)>> {
+    if file.metadata()?.is_file() {
+        let mut file = file.try_clone()?;
+        let start = file.stream_position()?;
+        Ok(Some((file, start)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Fills in line and column of a position taken without line tracking.
+fn locate(rescan: Option<&(File, u64)>, pos: &mut Position) -> Result<()> {
+    if let Some((file, start)) = rescan {
+        let mut file = file;
+        file.seek(SeekFrom::Start(*start))?;
+        *pos = Position::locate(file, pos.offset)?;
+    }
+    Ok(())
+}
+
 fn consume_all(reader: &mut impl BufRead) -> Result<()> {
     loop {
         let buf = reader.fill_buf()?;
@@ -116,9 +566,24 @@ const BUF_SIZE: usize = 512 * 1024;
 static mut STD_BUF: Align16<[u8; BUF_SIZE]> = Align16([0u8; BUF_SIZE]);
 static mut USER_BUF: Align16<[u8; BUF_SIZE]> = Align16([0u8; BUF_SIZE]);
 
-fn handle_normal(common_opts: &CommonOpts) -> Result<Comparison> {
+#[cfg(unix)]
+type FdReader = ojcmp::UnixFdReader;
+#[cfg(not(unix))]
+type FdReader = File;
+
+fn with_byte_readers(
+    common_opts: &CommonOpts,
+    f: impl FnOnce(
+        &mut ByteReader<FdReader>,
+        &mut ByteReader<FdReader>,
+    ) -> Result<CompareReport, CompareError>,
+) -> Result<CompareReport> {
     let (std_file, user_file) = open(common_opts)?;
 
+    // Lines are counted only for a mismatch, by reading regular files again.
+    let std_rescan = rescan_handle(&std_file)?;
+    let user_rescan = rescan_handle(&user_file)?;
+
     let (mut std_reader, mut user_reader) = {
         #[cfg(unix)]
         let std_file = ojcmp::UnixFdReader::from_file(std_file);
@@ -141,12 +606,25 @@ fn handle_normal(common_opts: &CommonOpts) -> Result<Comparison> {
         }
     };
 
-    let ans = ojcmp::try_normal_compare(&mut std_reader, &mut user_reader)?;
+    std_reader.set_track_lines(std_rescan.is_none());
+    user_reader.set_track_lines(user_rescan.is_none());
 
-    if common_opts.read_all {
-        consume_all(&mut user_reader)?;
-    }
+    let ans = f(&mut std_reader, &mut user_reader).map_err(anyhow::Error::from);
+
+    let ans = match ans {
+        Ok(ans) if common_opts.read_all => consume_all(&mut user_reader).map(|()| ans),
+        ans => ans,
+    };
+
+    let ans = ans.and_then(|mut ans| {
+        if let Some(ref mut mismatch) = ans.mismatch {
+            locate(std_rescan.as_ref(), &mut mismatch.std)?;
+            locate(user_rescan.as_ref(), &mut mismatch.user)?;
+        }
+        Ok(ans)
+    });
 
+    // the static buffers must not be freed, even on error
     if common_opts.buffer_size <= BUF_SIZE {
         unsafe {
             let _ = std_reader.into_raw();
@@ -154,15 +632,34 @@ fn handle_normal(common_opts: &CommonOpts) -> Result<Comparison> {
         }
     }
 
-    Ok(ans)
+    ans
+}
+
+fn handle_normal(common_opts: &CommonOpts, text_opts: &TextOpts) -> Result<CompareReport> {
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_normal_compare(std_reader, user_reader, &text_opts.to_options())
+    })
+}
+
+fn handle_line(common_opts: &CommonOpts, text_opts: &TextOpts) -> Result<CompareReport> {
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_line_compare(std_reader, user_reader, &text_opts.to_options())
+    })
+}
+
+fn handle_token(common_opts: &CommonOpts, text_opts: &TextOpts) -> Result<CompareReport> {
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_token_compare(std_reader, user_reader, &text_opts.to_options())
+    })
 }
 
-fn handle_strict(common_opts: &CommonOpts) -> Result<Comparison> {
+fn handle_strict(common_opts: &CommonOpts, text_opts: &TextOpts) -> Result<CompareReport> {
     let (std_file, user_file) = open(common_opts)?;
     let mut std_reader = BufReader::with_capacity(common_opts.buffer_size, std_file);
     let mut user_reader = BufReader::with_capacity(common_opts.buffer_size, user_file);
 
-    let ans = ojcmp::try_strict_compare(&mut std_reader, &mut user_reader)?;
+    let ans =
+        ojcmp::try_strict_compare(&mut std_reader, &mut user_reader, &text_opts.to_options())?;
 
     if common_opts.read_all {
         consume_all(&mut user_reader)?;
@@ -171,66 +668,579 @@ fn handle_strict(common_opts: &CommonOpts) -> Result<Comparison> {
     Ok(ans)
 }
 
-fn handle_float(common_opts: &CommonOpts, eps: f64) -> Result<Comparison> {
-    let (std_file, user_file) = open(common_opts)?;
-    let mut std_reader = ByteReader::with_capacity(common_opts.buffer_size, std_file);
-    let mut user_reader = ByteReader::with_capacity(common_opts.buffer_size, user_file);
-
+fn check_eps(name: &str, eps: f64) -> Result<()> {
     anyhow::ensure!(
         (eps == 0.0 || eps.is_normal()) && !eps.is_nan(),
-        "eps is invalid: eps = {}",
+        "{} is invalid: {} = {}",
+        name,
+        name,
         eps
     );
 
-    anyhow::ensure!(eps >= 0.0, "eps must be non-negative: eps = {}", eps);
+    anyhow::ensure!(
+        eps >= 0.0,
+        "{} must be non-negative: {} = {}",
+        name,
+        name,
+        eps
+    );
 
-    let ans = ojcmp::try_float_compare(&mut std_reader, &mut user_reader, eps)?;
+    Ok(())
+}
 
-    if common_opts.read_all {
-        consume_all(&mut user_reader)?;
+fn handle_float(common_opts: &CommonOpts, float_opts: &FloatOpts) -> Result<CompareReport> {
+    let (eps, auto_eps) = match float_opts.eps {
+        None => (0.0, false),
+        Some(EpsOpt::Value(eps)) => (eps, false),
+        Some(EpsOpt::Auto) => (0.0, true),
+    };
+    let rel_eps = float_opts.rel_eps.unwrap_or(0.0);
+
+    check_eps("eps", eps)?;
+    check_eps("rel_eps", rel_eps)?;
+    for &eps in &float_opts.eps_columns {
+        check_eps("eps_columns", eps)?;
+    }
+    if auto_eps {
+        anyhow::ensure!(
+            float_opts.eps_columns.is_empty() && float_opts.norm.is_none(),
+            "eps auto cannot be used with eps_columns or norm"
+        );
     }
 
-    Ok(ans)
+    let mut options = FloatOptions::new(eps);
+    options.rel_eps = rel_eps;
+    options.max_ulps = float_opts.max_ulps;
+    options.eps_columns = float_opts.eps_columns.clone();
+    options.period = float_opts.period.and_then(|p| p.0);
+    options.period_columns = float_opts.period_columns.iter().map(|p| p.0).collect();
+    options.columns = float_opts.columns_by.0;
+    options.auto_eps = auto_eps;
+    options.norm = float_opts.norm.map(|n| n.0);
+    options.full_scan = float_opts.stats;
+    options.mixed = float_opts.mixed;
+    options.lines = float_opts.lines;
+    options.decimals = float_opts.decimals.map(|d| d.0);
+    options.decimals_verdict = float_opts.decimals_verdict.0;
+    options.sig_figs = match (float_opts.sig_figs, float_opts.min_sig_figs) {
+        (Some(n), _) => {
+            anyhow::ensure!(n > 0, "sig_figs must be positive: sig_figs = {}", n);
+            Some(SigFigs::Round(n))
+        }
+        (None, Some(n)) => Some(SigFigs::AtLeast(n)),
+        (None, None) => None,
+    };
+    options.literals = float_opts.literal_opts.to_policy();
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_float_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn handle_number(common_opts: &CommonOpts, number_opts: &NumberOpts) -> Result<CompareReport> {
+    let mut options = NumberOptions::new();
+    if let Some(ref tolerance) = number_opts.tolerance {
+        let tolerance = Decimal::parse(tolerance.as_bytes())
+            .with_context(|| format!("tolerance is invalid: tolerance = {}", tolerance))?;
+        anyhow::ensure!(
+            !tolerance.is_negative(),
+            "tolerance must be non-negative: tolerance = {}",
+            tolerance
+        );
+        options.tolerance = Some(tolerance);
+    }
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_number_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn handle_embedded(
+    common_opts: &CommonOpts,
+    embedded_opts: &EmbeddedOpts,
+) -> Result<CompareReport> {
+    let eps = embedded_opts.eps.unwrap_or(0.0);
+    let rel_eps = embedded_opts.rel_eps.unwrap_or(0.0);
+    check_eps("eps", eps)?;
+    check_eps("rel_eps", rel_eps)?;
+
+    let mut options = EmbeddedOptions::new(eps);
+    options.rel_eps = rel_eps;
+    options.max_memory = Some(embedded_opts.max_memory);
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_embedded_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn handle_fraction(
+    common_opts: &CommonOpts,
+    fraction_opts: &FractionOpts,
+) -> Result<CompareReport> {
+    let mut options = FractionOptions::new();
+    options.reduced = fraction_opts.reduced;
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_fraction_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn handle_unordered(
+    common_opts: &CommonOpts,
+    unordered_opts: &UnorderedOpts,
+) -> Result<CompareReport> {
+    let mut options = UnorderedOptions::new(unordered_opts.by.0);
+    options.collapse_spaces = unordered_opts.collapse_spaces;
+    options.max_memory = Some(unordered_opts.max_memory);
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_unordered_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn handle_float_set(
+    common_opts: &CommonOpts,
+    float_set_opts: &FloatSetOpts,
+) -> Result<CompareReport> {
+    let eps = float_set_opts.eps.unwrap_or(0.0);
+    let rel_eps = float_set_opts.rel_eps.unwrap_or(0.0);
+    check_eps("eps", eps)?;
+    check_eps("rel_eps", rel_eps)?;
+    anyhow::ensure!(
+        float_set_opts.arity > 0,
+        "arity must be positive: arity = {}",
+        float_set_opts.arity
+    );
+
+    let mut options = FloatSetOptions::new(float_set_opts.arity, eps);
+    options.rel_eps = rel_eps;
+    options.max_memory = Some(float_set_opts.max_memory);
+
+    with_byte_readers(common_opts, |std_reader, user_reader| {
+        ojcmp::try_float_set_compare(std_reader, user_reader, &options)
+    })
+}
+
+fn verdict_str(ans: Comparison) -> &'static str {
+    match ans {
+        Comparison::AC => "AC",
+        Comparison::WA => "WA",
+        Comparison::PE => "PE",
+    }
+}
+
+fn reason_str(reason: MismatchReason) -> &'static str {
+    match reason {
+        MismatchReason::ExtraOutput => "extra-output",
+        MismatchReason::MissingOutput => "missing-output",
+        MismatchReason::TokenMismatch => "token-mismatch",
+        MismatchReason::LineBreakMismatch => "line-break-mismatch",
+        MismatchReason::WhitespaceOnly => "whitespace-only",
+        MismatchReason::InvalidToken => "invalid-token",
+        MismatchReason::FormatMismatch => "format-mismatch",
+    }
+}
+
+fn error_kind(err: &anyhow::Error) -> &'static str {
+    let too_large = err
+        .chain()
+        .any(|e| matches!(e.downcast_ref(), Some(CompareError::TooLarge)));
+    if too_large {
+        return "too-large";
+    }
+    let is_io = err
+        .chain()
+        .any(|e| e.is::<io::Error>() || e.is::<CompareError>());
+    if is_io {
+        "io"
+    } else {
+        "invalid-argument"
+    }
+}
+
+fn position_json(pos: Position) -> Value {
+    json!({
+        "offset": pos.offset,
+        "line": pos.line,
+        "column": pos.column,
+    })
+}
+
+/// JSON has no NaN or infinity, so they are written as "nan", "inf" and "-inf".
+fn f64_json(x: f64) -> Value {
+    if x.is_nan() {
+        json!("nan")
+    } else if x.is_infinite() {
+        json!(if x > 0.0 { "inf" } else { "-inf" })
+    } else {
+        json!(x)
+    }
+}
+
+fn opt_f64_json(x: Option<f64>) -> Value {
+    x.map_or(Value::Null, f64_json)
+}
+
+fn float_mismatch_json(m: &FloatMismatch) -> Value {
+    json!({
+        "token_index": m.token_index,
+        "std_literal": m.std_literal,
+        "user_literal": m.user_literal,
+        "std_value": opt_f64_json(m.std_value),
+        "user_value": opt_f64_json(m.user_value),
+        "abs_error": opt_f64_json(m.abs_error),
+        "rel_error": opt_f64_json(m.rel_error),
+        "inferred_eps": opt_f64_json(m.inferred_eps),
+    })
+}
+
+fn float_error_json(e: &Option<FloatError>) -> Value {
+    match e {
+        Some(e) => json!({ "error": f64_json(e.error), "token_index": e.token_index }),
+        None => Value::Null,
+    }
+}
+
+fn float_stats_json(stats: &FloatStats) -> Value {
+    json!({
+        "compared_tokens": stats.compared_tokens,
+        "failed_tokens": stats.failed_tokens,
+        "max_abs_error": float_error_json(&stats.max_abs_error),
+        "max_rel_error": float_error_json(&stats.max_rel_error),
+    })
+}
+
+fn float_stats_text(stats: &FloatStats) -> String {
+    fn error_at(e: &Option<FloatError>) -> String {
+        match e {
+            Some(e) => format!("{} (token {})", e.error, e.token_index),
+            None => "n/a".to_owned(),
+        }
+    }
+
+    format!(
+        "{} of {} tokens failed, max abs error {}, max rel error {}",
+        stats.failed_tokens,
+        stats.compared_tokens,
+        error_at(&stats.max_abs_error),
+        error_at(&stats.max_rel_error),
+    )
+}
+
+fn norm_str(norm: Norm) -> &'static str {
+    match norm {
+        Norm::L2 => "l2",
+        Norm::LInf => "linf",
+    }
+}
+
+fn float_norm_json(norm: &FloatNorm) -> Value {
+    json!({
+        "norm": norm_str(norm.norm),
+        "compared_tokens": norm.compared_tokens,
+        "abs_error": f64_json(norm.abs_error),
+        "rel_error": opt_f64_json(norm.rel_error),
+    })
+}
+
+fn float_norm_text(norm: &FloatNorm) -> String {
+    format!(
+        "{} norm of {} tokens: abs error {}, rel error {}",
+        norm_str(norm.norm),
+        norm.compared_tokens,
+        norm.abs_error,
+        or_na(norm.rel_error),
+    )
+}
+
+/// Formats `{head}: std {std} (line n), user {user} (line n)` followed by `details`,
+/// where the lines come from the mismatch of `report`.
+fn mismatch_text(
+    head: &str,
+    std: &str,
+    user: &str,
+    details: &[String],
+    report: &CompareReport,
+) -> String {
+    let (std_line, user_line) = match report.mismatch {
+        Some(ref pos) => (pos.std.line, pos.user.line),
+        None => (0, 0),
+    };
+    let mut text = format!(
+        "{}: std {} (line {}), user {} (line {})",
+        head, std, std_line, user, user_line,
+    );
+    for detail in details {
+        text += ", ";
+        text += detail;
+    }
+    text
+}
+
+/// Quotes a token literal, or `"<EOF>"` if the side has ended.
+fn or_eof(literal: &Option<String>) -> String {
+    format!("{:?}", literal.as_deref().unwrap_or("<EOF>"))
+}
+
+fn or_na(x: Option<impl ToString>) -> String {
+    x.map_or_else(|| "n/a".to_owned(), |x| x.to_string())
+}
+
+fn float_mismatch_text(m: &FloatMismatch, report: &CompareReport) -> String {
+    let mut details = vec![
+        format!("std value {}", or_na(m.std_value)),
+        format!("user value {}", or_na(m.user_value)),
+        format!("abs error {}", or_na(m.abs_error)),
+        format!("rel error {}", or_na(m.rel_error)),
+    ];
+    if let Some(eps) = m.inferred_eps {
+        details.push(format!("inferred eps {}", eps));
+    }
+    mismatch_text(
+        &format!("token {}", m.token_index),
+        &or_eof(&m.std_literal),
+        &or_eof(&m.user_literal),
+        &details,
+        report,
+    )
+}
+
+fn number_mismatch_json(m: &NumberMismatch) -> Value {
+    json!({
+        "token_index": m.token_index,
+        "std_literal": m.std_literal,
+        "user_literal": m.user_literal,
+        "abs_error": m.abs_error.as_ref().map(ToString::to_string),
+    })
+}
+
+fn number_mismatch_text(m: &NumberMismatch, report: &CompareReport) -> String {
+    mismatch_text(
+        &format!("token {}", m.token_index),
+        &or_eof(&m.std_literal),
+        &or_eof(&m.user_literal),
+        &[format!("abs error {}", or_na(m.abs_error.as_ref()))],
+        report,
+    )
+}
+
+fn fraction_mismatch_json(m: &FractionMismatch) -> Value {
+    json!({
+        "token_index": m.token_index,
+        "std_literal": m.std_literal,
+        "user_literal": m.user_literal,
+    })
+}
+
+fn fraction_mismatch_text(m: &FractionMismatch, report: &CompareReport) -> String {
+    mismatch_text(
+        &format!("token {}", m.token_index),
+        &or_eof(&m.std_literal),
+        &or_eof(&m.user_literal),
+        &[],
+        report,
+    )
+}
+
+fn unordered_mismatch_json(m: &UnorderedMismatch) -> Value {
+    json!({
+        "std_element": m.std_element,
+        "user_element": m.user_element,
+    })
+}
+
+fn unordered_mismatch_text(m: &UnorderedMismatch, report: &CompareReport) -> String {
+    fn or_none(element: &Option<String>) -> String {
+        match element {
+            Some(e) => format!("{:?}", e),
+            None => "<none>".to_owned(),
+        }
+    }
+
+    mismatch_text(
+        "unmatched",
+        &or_none(&m.std_element),
+        &or_none(&m.user_element),
+        &[],
+        report,
+    )
+}
+
+fn report_json(mode: &str, ret: &Result<CompareReport>) -> Value {
+    match ret {
+        Ok(report) => json!({
+            "mode": mode,
+            "verdict": verdict_str(report.comparison),
+            "error": null,
+            "mismatch": report.mismatch.map(|m| json!({
+                "std": position_json(m.std),
+                "user": position_json(m.user),
+                "reason": reason_str(m.reason),
+            })),
+            "std_consumed": report.std_consumed,
+            "user_consumed": report.user_consumed,
+            "float": report.float_mismatch.as_ref().map(float_mismatch_json),
+            "float_stats": report.float_stats.as_ref().map(float_stats_json),
+            "float_norm": report.float_norm.as_ref().map(float_norm_json),
+            "number": report.number_mismatch.as_ref().map(number_mismatch_json),
+            "fraction": report.fraction_mismatch.as_ref().map(fraction_mismatch_json),
+            "unordered": report.unordered_mismatch.as_ref().map(unordered_mismatch_json),
+        }),
+        Err(err) => json!({
+            "mode": mode,
+            "verdict": null,
+            "error": {
+                "kind": error_kind(err),
+                "message": format!("{:#}", err),
+            },
+            "mismatch": null,
+            "std_consumed": null,
+            "user_consumed": null,
+            "float": null,
+            "float_stats": null,
+            "float_norm": null,
+            "number": null,
+            "fraction": null,
+            "unordered": null,
+        }),
+    }
 }
 
 fn main() {
     let opts: Opts = Opts::from_args();
 
-    let (common_opts, ret) = match opts {
-        Opts::Normal { ref common_opts } => {
-            (common_opts, handle_normal(common_opts)) //
+    let (mode, common_opts, ret) = match opts {
+        Opts::Normal {
+            ref common_opts,
+            ref text_opts,
+        } => {
+            ("normal", common_opts, handle_normal(common_opts, text_opts)) //
         }
-        Opts::Strict { ref common_opts } => {
-            (common_opts, handle_strict(common_opts)) //
+        Opts::Line {
+            ref common_opts,
+            ref text_opts,
+        } => {
+            ("line", common_opts, handle_line(common_opts, text_opts)) //
+        }
+        Opts::Token {
+            ref common_opts,
+            ref text_opts,
+        } => {
+            ("token", common_opts, handle_token(common_opts, text_opts)) //
+        }
+        Opts::Strict {
+            ref common_opts,
+            ref text_opts,
+        } => {
+            ("strict", common_opts, handle_strict(common_opts, text_opts)) //
         }
         Opts::Float {
             ref common_opts,
-            eps,
+            ref float_opts,
         } => {
-            (common_opts, handle_float(common_opts, eps)) //
+            ("float", common_opts, handle_float(common_opts, float_opts)) //
         }
+        Opts::Number {
+            ref common_opts,
+            ref number_opts,
+        } => {
+            (
+                "number",
+                common_opts,
+                handle_number(common_opts, number_opts),
+            ) //
+        }
+        Opts::Embedded {
+            ref common_opts,
+            ref embedded_opts,
+        } => (
+            "embedded",
+            common_opts,
+            handle_embedded(common_opts, embedded_opts),
+        ),
+        Opts::Fraction {
+            ref common_opts,
+            ref fraction_opts,
+        } => (
+            "fraction",
+            common_opts,
+            handle_fraction(common_opts, fraction_opts),
+        ),
+        Opts::Unordered {
+            ref common_opts,
+            ref unordered_opts,
+        } => (
+            "unordered",
+            common_opts,
+            handle_unordered(common_opts, unordered_opts),
+        ),
+        Opts::FloatSet {
+            ref common_opts,
+            ref float_set_opts,
+        } => (
+            "float-set",
+            common_opts,
+            handle_float_set(common_opts, float_set_opts),
+        ),
     };
 
-    let exit_code = match ret {
-        Ok(ans) => {
-            if !common_opts.quiet {
-                let output = match ans {
-                    Comparison::AC => "AC",
-                    Comparison::WA => "WA",
-                    Comparison::PE => "PE",
-                };
+    let mut exit_code = match ret {
+        Ok(ref report) => report.comparison as i32,
+        Err(_) => 101,
+    };
 
-                println!("{}", output);
+    let output = match common_opts.format {
+        Format::Text => match ret {
+            Ok(ref report) => {
+                if !common_opts.quiet {
+                    if let Some(ref m) = report.float_mismatch {
+                        eprintln!("{}", float_mismatch_text(m, report));
+                    }
+                    if let Some(ref stats) = report.float_stats {
+                        eprintln!("{}", float_stats_text(stats));
+                    }
+                    if let Some(ref norm) = report.float_norm {
+                        eprintln!("{}", float_norm_text(norm));
+                    }
+                    if let Some(ref m) = report.number_mismatch {
+                        eprintln!("{}", number_mismatch_text(m, report));
+                    }
+                    if let Some(ref m) = report.fraction_mismatch {
+                        eprintln!("{}", fraction_mismatch_text(m, report));
+                    }
+                    if let Some(ref m) = report.unordered_mismatch {
+                        eprintln!("{}", unordered_mismatch_text(m, report));
+                    }
+                }
+                Some(verdict_str(report.comparison).to_owned())
             }
-            ans as i32
-        }
-        Err(err) => {
-            if !common_opts.quiet {
-                eprintln!("{}", err);
+            Err(ref err) => {
+                if !common_opts.quiet {
+                    eprintln!("{}", err);
+                }
+                None
             }
-            101
-        }
+        },
+        Format::Json => Some(report_json(mode, &ret).to_string()),
     };
 
+    if let Some(output) = output {
+        match common_opts.result_file {
+            Some(ref path) => {
+                if let Err(err) = fs::write(path, output + "\n") {
+                    if !common_opts.quiet {
+                        eprintln!("failed to write result file: {:?}: {}", path, err);
+                    }
+                    exit_code = 101;
+                }
+            }
+            None => {
+                if !common_opts.quiet {
+                    println!("{}", output);
+                }
+            }
+        }
+    }
+
     process::exit(exit_code)
 }